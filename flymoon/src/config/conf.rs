@@ -60,6 +60,8 @@ pub fn reset(mut conf: Config) {
     conf.config_path.clone_from(&c.config_path);
     conf.db_path.clone_from(&c.db_path);
     conf.cache_dir.clone_from(&c.cache_dir);
+    conf.mcp_presets_dir.clone_from(&c.mcp_presets_dir);
+    conf.exports_dir.clone_from(&c.exports_dir);
     conf.is_first_run = c.is_first_run;
 
     *c = conf;
@@ -74,6 +76,16 @@ pub fn model() -> data::Model {
     CONFIG.lock().unwrap().model.clone()
 }
 
+#[allow(dead_code)]
+pub fn builtin_tools() -> data::BuiltinTools {
+    CONFIG.lock().unwrap().builtin_tools.clone()
+}
+
+#[allow(dead_code)]
+pub fn secret(name: &str) -> Option<String> {
+    CONFIG.lock().unwrap().secrets.get(name).cloned()
+}
+
 #[cfg(feature = "database")]
 pub fn db_path() -> PathBuf {
     CONFIG.lock().unwrap().db_path.clone()
@@ -84,6 +96,16 @@ pub fn cache_dir() -> PathBuf {
     CONFIG.lock().unwrap().cache_dir.clone()
 }
 
+#[allow(dead_code)]
+pub fn mcp_presets_dir() -> PathBuf {
+    CONFIG.lock().unwrap().mcp_presets_dir.clone()
+}
+
+#[allow(dead_code)]
+pub fn exports_dir() -> PathBuf {
+    CONFIG.lock().unwrap().exports_dir.clone()
+}
+
 pub fn save(conf: data::Config) -> Result<()> {
     let mut config = CONFIG.lock().unwrap();
     *config = conf;
@@ -135,6 +157,8 @@ impl Config {
         self.db_path = app_dirs.data_dir.join(format!("{}.db", self.app_name));
         self.config_path = app_dirs.config_dir.join(format!("{}.toml", self.app_name));
         self.cache_dir = app_dirs.data_dir.join("cache");
+        self.mcp_presets_dir = app_dirs.config_dir.join("mcp-presets");
+        self.exports_dir = app_dirs.data_dir.join("exports");
 
         if self.appid.is_empty() {
             self.appid = super::data::appid_default();
@@ -143,6 +167,8 @@ impl Config {
         fs::create_dir_all(&app_dirs.data_dir)?;
         fs::create_dir_all(&app_dirs.config_dir)?;
         fs::create_dir_all(&self.cache_dir)?;
+        fs::create_dir_all(&self.mcp_presets_dir)?;
+        fs::create_dir_all(&self.exports_dir)?;
 
         Ok(())
     }
@@ -154,6 +180,8 @@ impl Config {
                     self.appid = c.appid;
                     self.preference = c.preference;
                     self.model = c.model;
+                    self.builtin_tools = c.builtin_tools;
+                    self.secrets = c.secrets;
                     Ok(())
                 }
                 Err(_) => {
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
@@ -13,6 +13,14 @@ pub struct Config {
     #[serde(skip)]
     pub cache_dir: PathBuf,
 
+    // the user MCP preset catalogs
+    #[serde(skip)]
+    pub mcp_presets_dir: PathBuf,
+
+    // the exported files, e.g. the MCP audit log. It's kept when the cache is removed
+    #[serde(skip)]
+    pub exports_dir: PathBuf,
+
     #[serde(skip)]
     pub is_first_run: bool,
 
@@ -25,6 +33,13 @@ pub struct Config {
     pub preference: Preference,
 
     pub model: Model,
+
+    #[serde(default)]
+    pub builtin_tools: BuiltinTools,
+
+    // referenced as `${secret:NAME}` in the MCP server configurations
+    #[serde(default)]
+    pub secrets: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Derivative)]
@@ -72,10 +87,80 @@ pub struct GoogleSearch {
     pub num: i32,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Derivative)]
+#[derivative(Default)]
+#[serde(default)]
+pub struct WebSearch {
+    // google, searxng, brave, bing or duckduckgo
+    #[derivative(Default(value = "\"google\".to_string()"))]
+    pub provider: String,
+
+    pub searxng_url: String,
+    pub brave_api_key: String,
+    pub bing_api_key: String,
+
+    // the links of a search with the providers other than google, which has its own number
+    #[derivative(Default(value = "5"))]
+    pub num: i32,
+
+    // the hours of caching the search results and the fetched pages, 0 means no cache
+    #[derivative(Default(value = "24"))]
+    pub cache_hours: u32,
+
+    // rewrite the question and the recent history into standalone search queries with the chat model
+    pub generate_queries: bool,
+
+    // the budget of the deep research
+    #[derivative(Default(value = "3"))]
+    pub research_max_steps: u32,
+
+    #[derivative(Default(value = "12"))]
+    pub research_max_pages: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Derivative)]
+#[derivative(Default)]
+pub struct ToolResult {
+    // the characters of a tool result which are resent to the model, 0 means no limit
+    #[derivative(Default(value = "8000"))]
+    pub max_chars: u32,
+
+    // summarize the oversized result with the chat model instead of truncating it
+    pub summarize: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Model {
     pub chat: ChatModel,
     pub google_search: GoogleSearch,
+
+    #[serde(default)]
+    pub web_search: WebSearch,
+
+    #[serde(default)]
+    pub tool_result: ToolResult,
+}
+
+impl Model {
+    pub fn is_web_search_available(&self) -> bool {
+        match self.web_search.provider.as_str() {
+            "searxng" => !self.web_search.searxng_url.trim().is_empty(),
+            "brave" => !self.web_search.brave_api_key.trim().is_empty(),
+            "bing" => !self.web_search.bing_api_key.trim().is_empty(),
+            "duckduckgo" => true,
+            _ => !self.google_search.api_key.trim().is_empty(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BuiltinTools {
+    pub allowed_dirs: Vec<String>,
+
+    // "Ask", "Allow" or "Deny". The tools without a policy are allowed,
+    // except the ones which read the local data are asked
+    #[serde(default)]
+    pub policies: HashMap<String, String>,
 }
 
 pub fn appid_default() -> String {
@@ -1,6 +1,9 @@
 mod conf;
 pub mod data;
-pub use conf::{all, cache_dir, app_name, init, is_first_run, model, preference, save};
+pub use conf::{
+    all, app_name, builtin_tools, cache_dir, exports_dir, init, is_first_run, mcp_presets_dir,
+    model, preference, save, secret,
+};
 
 #[cfg(feature = "database")]
 pub use conf::db_path;
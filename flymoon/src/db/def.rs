@@ -1,17 +1,20 @@
 use crate::slint_generatedAppWindow::{
     ChatEntry as UIChatEntry, ChatHistory, ChatSession as UIChatSession,
-    MCPElement as UIMCPElement, MCPEntry as UIMCPEntry, PromptEntry as UIPromptEntry, PromptType,
-    SearchLink as UISearchLink,
+    MCPAuditEntry as UIMCPAuditEntry, MCPElement as UIMCPElement, MCPEntry as UIMCPEntry,
+    MCPTool as UIMCPTool, MCPToolPermission as UIMCPToolPermission, MCPToolPolicy,
+    PromptEntry as UIPromptEntry, PromptType, SearchLink as UISearchLink,
 };
 use search::SearchLink;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use slint::{Model, ModelRc, VecModel};
+use slint::{Model, ModelRc, SharedString, VecModel};
+use sqldb::audit::AuditEntry;
 use std::fmt;
 
 pub const PROMPT_TABLE: &str = "prompt";
 pub const MCP_TABLE: &str = "mcp";
 pub const CHAT_SESSION_TABLE: &str = "chat_session";
+pub const MCP_AUDIT_TABLE: &str = "mcp_audit";
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct PromptEntry {
@@ -56,6 +59,66 @@ impl From<UIMCPEntry> for UIPromptEntry {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MCPToolPermission {
+    pub tool_name: String,
+    pub policy: MCPToolPolicy,
+}
+
+impl From<UIMCPToolPermission> for MCPToolPermission {
+    fn from(entry: UIMCPToolPermission) -> Self {
+        MCPToolPermission {
+            tool_name: entry.tool_name.into(),
+            policy: entry.policy,
+        }
+    }
+}
+
+impl From<MCPToolPermission> for UIMCPToolPermission {
+    fn from(entry: MCPToolPermission) -> Self {
+        UIMCPToolPermission {
+            tool_name: entry.tool_name.into(),
+            policy: entry.policy,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MCPTool {
+    pub name: String,
+
+    #[serde(default)]
+    pub default_description: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default = "mcp_default_tool_enabled")]
+    pub enabled: bool,
+}
+
+impl From<UIMCPTool> for MCPTool {
+    fn from(entry: UIMCPTool) -> Self {
+        MCPTool {
+            name: entry.name.into(),
+            default_description: entry.default_description.into(),
+            description: entry.description.into(),
+            enabled: entry.enabled,
+        }
+    }
+}
+
+impl From<MCPTool> for UIMCPTool {
+    fn from(entry: MCPTool) -> Self {
+        UIMCPTool {
+            name: entry.name.into(),
+            default_description: entry.default_description.into(),
+            description: entry.description.into(),
+            enabled: entry.enabled,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct MCPEntry {
     pub uuid: String,
@@ -65,6 +128,21 @@ pub struct MCPEntry {
 
     #[serde(default = "mcp_default_temperature")]
     pub temperature: f32,
+
+    #[serde(default)]
+    pub policy: MCPToolPolicy,
+
+    #[serde(default)]
+    pub tool_permissions: Vec<MCPToolPermission>,
+
+    // The enabled tools are the allowed ones, and the tools which are not in
+    // the list are not allowed. Empty means the list is never edited, so all
+    // the tools are allowed.
+    #[serde(default)]
+    pub tools: Vec<MCPTool>,
+
+    #[serde(default)]
+    pub prompt_template: String,
 }
 
 impl From<UIMCPEntry> for MCPEntry {
@@ -75,18 +153,50 @@ impl From<UIMCPEntry> for MCPEntry {
             shortcut: entry.shortcut.into(),
             config: entry.config.into(),
             temperature: entry.temperature,
+            policy: entry.policy,
+            tool_permissions: entry
+                .tool_permissions
+                .iter()
+                .map(|item| item.into())
+                .collect::<Vec<MCPToolPermission>>(),
+            tools: entry
+                .tools
+                .iter()
+                .map(|item| item.into())
+                .collect::<Vec<MCPTool>>(),
+            prompt_template: entry.prompt_template.into(),
         }
     }
 }
 
 impl From<MCPEntry> for UIMCPEntry {
     fn from(entry: MCPEntry) -> Self {
+        let tool_permissions = ModelRc::new(
+            entry
+                .tool_permissions
+                .into_iter()
+                .map(|item| item.into())
+                .collect::<VecModel<UIMCPToolPermission>>(),
+        );
+
+        let tools = ModelRc::new(
+            entry
+                .tools
+                .into_iter()
+                .map(|item| item.into())
+                .collect::<VecModel<UIMCPTool>>(),
+        );
+
         UIMCPEntry {
             uuid: entry.uuid.into(),
             name: entry.name.into(),
             shortcut: entry.shortcut.into(),
             config: entry.config.into(),
             temperature: entry.temperature,
+            policy: entry.policy,
+            tool_permissions,
+            tools,
+            prompt_template: entry.prompt_template.into(),
             ..Default::default()
         }
     }
@@ -94,8 +204,23 @@ impl From<MCPEntry> for UIMCPEntry {
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct MCPElement {
-    tool_name: String,
-    resp: String,
+    pub tool_name: String,
+    pub resp: String,
+
+    #[serde(default)]
+    pub is_error: bool,
+
+    #[serde(default)]
+    pub context: String,
+
+    // Empty in the entries which are saved before the tool calls are recorded.
+    // They are assigned by `ChatEntry::migrate` when loading.
+    #[serde(default)]
+    pub tool_call_id: String,
+
+    // JSON object
+    #[serde(default)]
+    pub arguments: String,
 }
 
 impl From<MCPElement> for UIMCPElement {
@@ -103,6 +228,10 @@ impl From<MCPElement> for UIMCPElement {
         UIMCPElement {
             tool_name: entry.tool_name.into(),
             resp: entry.resp.into(),
+            is_error: entry.is_error,
+            context: entry.context.into(),
+            tool_call_id: entry.tool_call_id.into(),
+            arguments: entry.arguments.into(),
         }
     }
 }
@@ -112,16 +241,54 @@ impl From<UIMCPElement> for MCPElement {
         MCPElement {
             tool_name: entry.tool_name.into(),
             resp: entry.resp.into(),
+            is_error: entry.is_error,
+            context: entry.context.into(),
+            tool_call_id: entry.tool_call_id.into(),
+            arguments: entry.arguments.into(),
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ChatEntry {
-    user: String,
-    bot: String,
-    mcp: Vec<MCPElement>,
-    search_links: Vec<SearchLink>,
+    pub user: String,
+    pub bot: String,
+    pub mcp: Vec<MCPElement>,
+    pub search_links: Vec<SearchLink>,
+
+    // the queries which are generated by the model for the web search
+    #[serde(default)]
+    pub search_queries: Vec<String>,
+
+    // the notes of each step of the deep research
+    #[serde(default)]
+    pub research_notes: Vec<String>,
+
+    // the links in the question whose contents are sent to the model
+    #[serde(default)]
+    pub url_attachments: Vec<SearchLink>,
+
+    // the retrieved web contents which are replayed with the entry
+    #[serde(default)]
+    pub web_context: String,
+}
+
+impl ChatEntry {
+    // Fill the fields which are missing in the old entries.
+    // The tool call ids are unique in the session, so the index of the entry is a part of them.
+    pub fn migrate(mut self, entry_index: usize) -> Self {
+        for (index, item) in self.mcp.iter_mut().enumerate() {
+            if item.tool_call_id.is_empty() {
+                item.tool_call_id = format!("call_{entry_index}_{index}");
+            }
+
+            if item.arguments.is_empty() {
+                item.arguments = "{}".to_string();
+            }
+        }
+
+        self
+    }
 }
 
 impl From<UIChatEntry> for ChatEntry {
@@ -141,6 +308,22 @@ impl From<UIChatEntry> for ChatEntry {
                 .map(|entry| entry.into())
                 .collect::<Vec<MCPElement>>(),
             search_links,
+            search_queries: entry
+                .search_queries
+                .iter()
+                .map(|item| item.into())
+                .collect(),
+            research_notes: entry
+                .research_notes
+                .iter()
+                .map(|item| item.into())
+                .collect(),
+            url_attachments: entry
+                .url_attachments
+                .iter()
+                .map(|item| item.into())
+                .collect(),
+            web_context: entry.web_context.into(),
         }
     }
 }
@@ -163,11 +346,39 @@ impl From<ChatEntry> for UIChatEntry {
                 .collect::<VecModel<UIMCPElement>>(),
         );
 
+        let search_queries = ModelRc::new(
+            entry
+                .search_queries
+                .into_iter()
+                .map(|item| item.into())
+                .collect::<VecModel<SharedString>>(),
+        );
+
+        let research_notes = ModelRc::new(
+            entry
+                .research_notes
+                .into_iter()
+                .map(|item| item.into())
+                .collect::<VecModel<SharedString>>(),
+        );
+
+        let url_attachments = ModelRc::new(
+            entry
+                .url_attachments
+                .into_iter()
+                .map(|item| item.into())
+                .collect::<VecModel<UISearchLink>>(),
+        );
+
         UIChatEntry {
             user: entry.user.into(),
             bot: entry.bot.into(),
             mcp,
             search_links,
+            search_queries,
+            research_notes,
+            url_attachments,
+            web_context: entry.web_context.into(),
             md_elems: ModelRc::new(VecModel::from(vec![])),
             link_urls: ModelRc::new(VecModel::from(vec![])),
             ..Default::default()
@@ -182,6 +393,11 @@ pub struct ChatSession {
     pub prompt: String,
     pub mcp_config: String,
     pub prompt_type: PromptType,
+
+    // names of the enabled built-in tools
+    #[serde(default)]
+    pub builtin_tools: Vec<String>,
+
     pub histories: Vec<ChatEntry>,
 }
 
@@ -244,6 +460,73 @@ impl<'de> Deserialize<'de> for PromptType {
     }
 }
 
+impl Serialize for MCPToolPolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            MCPToolPolicy::Ask => serializer.serialize_str("Ask"),
+            MCPToolPolicy::Allow => serializer.serialize_str("Allow"),
+            MCPToolPolicy::Deny => serializer.serialize_str("Deny"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MCPToolPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MCPToolPolicyVisitor;
+
+        impl<'de> Visitor<'de> for MCPToolPolicyVisitor {
+            type Value = MCPToolPolicy;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter
+                    .write_str("a string representing MCPToolPolicy ('Ask', 'Allow' or 'Deny')")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<MCPToolPolicy, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "Ask" => Ok(MCPToolPolicy::Ask),
+                    "Allow" => Ok(MCPToolPolicy::Allow),
+                    "Deny" => Ok(MCPToolPolicy::Deny),
+                    _ => Err(E::custom(format!(
+                        "unknown MCPToolPolicy variant: {}",
+                        value
+                    ))),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(MCPToolPolicyVisitor)
+    }
+}
+
+fn mcp_default_tool_enabled() -> bool {
+    true
+}
+
 fn mcp_default_temperature() -> f32 {
     return 0.7;
 }
+
+impl From<AuditEntry> for UIMCPAuditEntry {
+    fn from(entry: AuditEntry) -> Self {
+        UIMCPAuditEntry {
+            timestamp: entry.timestamp.into(),
+            session_uuid: entry.session_uuid.into(),
+            server: entry.server.into(),
+            tool: entry.tool.into(),
+            arguments: entry.arguments.into(),
+            result_size: entry.result_size as i32,
+            duration_ms: entry.duration_ms as i32,
+            outcome: entry.outcome.into(),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 pub mod def;
 
-pub use sqldb::{create_db, entry};
+pub use sqldb::{audit, create_db, entry};
 
 pub async fn init(db_path: &str) {
     create_db(db_path).await.expect("create db");
@@ -16,4 +16,8 @@ pub async fn init(db_path: &str) {
     entry::new(def::CHAT_SESSION_TABLE)
         .await
         .expect("chat session table failed");
+
+    audit::new(def::MCP_AUDIT_TABLE)
+        .await
+        .expect("mcp audit table failed");
 }
@@ -4,5 +4,10 @@
 #[tokio::main]
 async fn main() {
     extern crate flymoon;
-    flymoon::desktop_main().await;
+
+    match std::env::args().nth(1).as_deref() {
+        Some("mcp-serve") => flymoon::mcp_serve_main().await,
+        Some(search::document::PDF_TEXT_COMMAND) => flymoon::pdf_text_main(),
+        _ => flymoon::desktop_main().await,
+    }
 }
@@ -41,6 +41,12 @@ mod version;
 #[cfg(feature = "database")]
 mod db;
 
+#[cfg(all(
+    feature = "database",
+    any(target_os = "windows", target_os = "linux", target_os = "macos")
+))]
+mod mcp_serve;
+
 mod logic;
 
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
@@ -137,6 +143,11 @@ async fn android_main(app: slint::android::AndroidApp) {
 pub async fn desktop_main() {
     log::debug!("start...");
 
+    // the PDFs are extracted by this program with the `pdf-text` command
+    if let Ok(program) = std::env::current_exe() {
+        search::document::set_pdf_text_program(program);
+    }
+
     ui_before().await;
     let ui = AppWindow::new().unwrap();
     ui.global::<Store>().set_device_type(DeviceType::Desktop);
@@ -145,10 +156,33 @@ pub async fn desktop_main() {
     ui.global::<Util>().invoke_set_window_center();
 
     ui.run().unwrap();
+    mcp::shutdown_all().await;
 
     log::debug!("exit...");
 }
 
+// Run as a headless MCP server over stdio, stdout is reserved for the protocol
+#[cfg(all(
+    feature = "database",
+    any(target_os = "windows", target_os = "linux", target_os = "macos")
+))]
+pub async fn mcp_serve_main() {
+    ui_before().await;
+
+    if let Err(e) = mcp_serve::serve().await {
+        log::error!("{e:?}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+pub fn pdf_text_main() {
+    if let Err(e) = search::document::pdf_text_main() {
+        eprintln!("{e:?}");
+        std::process::exit(1);
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen(start))]
 pub fn main() {
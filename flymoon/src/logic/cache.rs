@@ -0,0 +1,57 @@
+use super::tr::tr;
+use crate::{
+    config,
+    slint_generatedAppWindow::{AppWindow, Logic, Store},
+    toast_success, toast_warn,
+};
+use cutil::{fs::remove_dir_files, str::pretty_size_string};
+use slint::ComponentHandle;
+use std::{fs, path::PathBuf, time::Duration};
+
+const SEARCH_CACHE_DIR: &str = "search";
+
+fn search_cache_dir() -> PathBuf {
+    config::cache_dir().join(SEARCH_CACHE_DIR)
+}
+
+pub fn init(ui: &AppWindow) {
+    init_search_cache();
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_update_cache_size(move || {
+        let ui = ui_handle.unwrap();
+        let size = search::cache::dir_size(&config::cache_dir());
+        ui.global::<Store>()
+            .set_cache_size(pretty_size_string(size).into());
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_remove_all_cache(move || {
+        let ui = ui_handle.unwrap();
+
+        let result = remove_dir_files(&config::cache_dir().to_string_lossy()).and_then(|_| {
+            match fs::remove_dir_all(search_cache_dir()) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        });
+
+        match result {
+            Err(e) => toast_warn!(ui, format!("{}. {e}", tr("Remove cache failed"))),
+            _ => toast_success!(ui, tr("Remove cache successfully")),
+        }
+
+        ui.global::<Logic>().invoke_update_cache_size();
+    });
+
+    ui.global::<Logic>().invoke_update_cache_size();
+}
+
+// Apply the cache hours of the web search setting
+pub fn init_search_cache() {
+    let hours = config::model().web_search.cache_hours;
+    search::cache::init(
+        search_cache_dir(),
+        Duration::from_secs(u64::from(hours) * 3600),
+    );
+}
@@ -1,36 +1,78 @@
-use super::{md, toast, tr::tr};
+use super::{
+    clipboard,
+    mcp::{self as mcp_logic, ToolPromptSettings},
+    md, research, toast,
+    tr::tr,
+};
 use crate::{
-    config::{data::Model as SettingModel, model as setting_model},
+    config::{self, data::Model as SettingModel, model as setting_model},
     db::{
         self,
         def::{ChatEntry, ChatSession, CHAT_SESSION_TABLE as DB_TABLE},
     },
     slint_generatedAppWindow::{
-        AppWindow, ChatEntry as UIChatEntry, ChatPhase, ChatSession as UIChatSession, Logic,
-        MCPElement as UIMCPElement, MCPEntry as UIMCPEntry, PromptEntry as UIPromptEntry,
-        PromptType, SearchLink as UISearchLink, Store,
+        AppWindow, BuiltinTool as UIBuiltinTool, ChatEntry as UIChatEntry, ChatPhase,
+        ChatSession as UIChatSession, Logic, MCPElement as UIMCPElement, MCPEntry as UIMCPEntry,
+        PromptEntry as UIPromptEntry, PromptType, SearchLink as UISearchLink, Store,
     },
     store_mcp_entries, store_prompt_entries, toast_success, toast_warn,
 };
 use bot::openai::{
-    request::{APIConfig as ChatAPIConfig, HistoryChat},
+    request::{APIConfig as ChatAPIConfig, HistoryChat, HistoryToolCall},
     response::StreamTextItem,
     Chat,
 };
 use cutil::time::chrono::{DateTime, Utc};
+use mcp::{
+    builtin::{self, BuiltinOptions},
+    tool::{CallOptions, ToolContent, ToolOutput, ToolSet},
+};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use search::{rank::RankOptions, SearchProvider};
 use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
-use std::sync::{
-    atomic::{AtomicU64, Ordering},
-    mpsc, Arc, Mutex,
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
+use tokio::sync::watch;
 use uuid::Uuid;
 
+type ToolCallFuture = Pin<Box<dyn Future<Output = Option<anyhow::Result<ToolOutput>>> + Send>>;
+
+// The audit entry of an approved call is taken once: by the call when it
+// finishes, or as cancelled if the chat is stopped before the call runs
+type ToolCallAuditSlot = Arc<Mutex<Option<mcp_logic::ToolCallAudit>>>;
+
+// A tool call of the model which is recorded in the history
+struct ToolCallRecord {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+// The tools which the model can call in a chat
+struct ChatTools {
+    tool_set: ToolSet,
+    options: CallOptions,
+    mcp_config: SharedString,
+
+    // Hold the server until the tool calls of the chat finish,
+    // so that it isn't stopped by the idle timeout while waiting for the permissions
+    _usage: Option<mcp::UsageGuard>,
+}
+
 struct ChatCache {
     id: u64,
     ui: Weak<AppWindow>,
     stop_tx: Arc<mpsc::Sender<()>>,
+    cancel_tx: watch::Sender<bool>,
     reasoner_start: Option<DateTime<Utc>>,
     bot_text: String,
 }
@@ -38,9 +80,44 @@ struct ChatCache {
 const MCP_TOOL_START_SEP: &'static str = "```";
 const MCP_TOOL_END_SEP: &'static str = "```";
 
+// The server name of the built-in tools in the audit log
+const BUILTIN_SERVER_NAME: &str = "builtin";
+
+// The oversized tool result is truncated before summarizing,
+// so that it fits in the context of the model
+const SUMMARIZE_INPUT_MAX_CHARS: usize = 100_000;
+
+// The recent turns and their max characters which are sent to the model
+// for generating the search queries
+const SEARCH_QUERIES_HISTORY_TURNS: usize = 3;
+const SEARCH_QUERIES_HISTORY_MAX_CHARS: usize = 1000;
+const SEARCH_QUERIES_MAX_COUNT: usize = 3;
+
+// The links in the question which are fetched, and the max characters of each page
+const QUESTION_URLS_MAX_COUNT: usize = 3;
+const QUESTION_URL_MAX_CHARS: usize = 20_000;
+const URL_ATTACHMENT_TITLE_MAX_CHARS: usize = 40;
+
+// The max characters of the web contents which are kept in an entry,
+// and of the ones which are replayed from the latest entries of the session
+const ENTRY_WEB_CONTEXT_MAX_CHARS: usize = 16_000;
+const REPLAY_WEB_CONTEXT_MAX_CHARS: usize = 32_000;
+
+const SEARCH_CONTEXT_INSTRUCTION: &str = "The following web contents are relevant to the user's question. Each item is a part of a web page, tagged with the number of its source. Please consult these resources when preparing your answer, and cite the sources with their numbers in square brackets right after the statements they support, e.g. [1] or [1][3].";
+const URL_CONTEXT_INSTRUCTION: &str =
+    "The following are the contents of the links in the user's question. Use them to answer the question.";
+const REPLAY_CONTEXT_INSTRUCTION: &str = "The following web contents were retrieved for the next question of the user. The source numbers in square brackets of the next answer refer to these sources only, not to the sources of the other questions.";
+
 static INC_CHAT_ID: AtomicU64 = AtomicU64::new(0);
 static CHAT_CACHE: Lazy<Mutex<Option<ChatCache>>> = Lazy::new(|| Mutex::new(None));
 
+// The images of tool results in the bot text are only for displaying
+static MCP_IMAGE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"!\[[^\]]*\]\(mcp-image://[^)]*\)").unwrap());
+
+// The numbering or bullet of a generated search query, e.g. `1.` or `-`
+static QUERY_PREFIX_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:\d+[.)]|[-*•])\s+").unwrap());
+
 #[macro_export]
 macro_rules! store_current_chat_session {
     ($ui:expr) => {
@@ -60,6 +137,18 @@ macro_rules! store_current_chat_session_histories {
     };
 }
 
+#[macro_export]
+macro_rules! store_current_chat_session_builtin_tools {
+    ($ui:expr) => {
+        $ui.global::<Store>()
+            .get_current_chat_session()
+            .builtin_tools
+            .as_any()
+            .downcast_ref::<VecModel<UIBuiltinTool>>()
+            .expect("We know we set a VecModel earlier")
+    };
+}
+
 #[macro_export]
 macro_rules! store_current_chat_session_histories_search_links {
     ($entry:expr) => {
@@ -71,6 +160,39 @@ macro_rules! store_current_chat_session_histories_search_links {
     };
 }
 
+#[macro_export]
+macro_rules! store_current_chat_session_histories_search_queries {
+    ($entry:expr) => {
+        $entry
+            .search_queries
+            .as_any()
+            .downcast_ref::<VecModel<SharedString>>()
+            .expect("We know we set a VecModel earlier")
+    };
+}
+
+#[macro_export]
+macro_rules! store_current_chat_session_histories_research_notes {
+    ($entry:expr) => {
+        $entry
+            .research_notes
+            .as_any()
+            .downcast_ref::<VecModel<SharedString>>()
+            .expect("We know we set a VecModel earlier")
+    };
+}
+
+#[macro_export]
+macro_rules! store_current_chat_session_histories_url_attachments {
+    ($entry:expr) => {
+        $entry
+            .url_attachments
+            .as_any()
+            .downcast_ref::<VecModel<UISearchLink>>()
+            .expect("We know we set a VecModel earlier")
+    };
+}
+
 #[macro_export]
 macro_rules! store_current_chat_session_histories_mcp {
     ($entry:expr) => {
@@ -93,33 +215,61 @@ impl From<SettingModel> for ChatAPIConfig {
     }
 }
 
-impl From<SettingModel> for search::google::Config {
-    fn from(setting: SettingModel) -> Self {
-        Self {
+pub fn search_provider(setting: SettingModel) -> Box<dyn SearchProvider> {
+    let num = setting.web_search.num as u8;
+
+    match setting.web_search.provider.as_str() {
+        "searxng" => Box::new(search::searxng::Config {
+            base_url: setting.web_search.searxng_url,
+            num,
+        }),
+        "brave" => Box::new(search::brave::Config {
+            api_key: setting.web_search.brave_api_key,
+            num,
+            ..Default::default()
+        }),
+        "bing" => Box::new(search::bing::Config {
+            api_key: setting.web_search.bing_api_key,
+            num,
+            ..Default::default()
+        }),
+        "duckduckgo" => Box::new(search::duckduckgo::Config {
+            num,
+            ..Default::default()
+        }),
+        _ => Box::new(search::google::Config {
             cx: setting.google_search.cx,
             api_key: setting.google_search.api_key,
             num: setting.google_search.num as u8,
-        }
+            ..Default::default()
+        }),
     }
 }
 
 impl From<UIChatEntry> for HistoryChat {
     fn from(entry: UIChatEntry) -> Self {
-        let mcp_resp = if entry.mcp.row_count() > 0 {
-            entry
-                .mcp
-                .iter()
-                .map(|entry| entry.resp.clone().to_string())
-                .collect::<String>()
-        } else {
-            String::default()
-        };
-
-        let btext = format!("{}\n\n{}", entry.bot, mcp_resp).into();
+        let max_chars = setting_model().tool_result.max_chars as usize;
+        let tool_calls = entry
+            .mcp
+            .iter()
+            .map(|item| HistoryToolCall {
+                id: item.tool_call_id.into(),
+                name: item.tool_name.into(),
+                arguments: item.arguments.into(),
+                result: if item.context.is_empty() {
+                    truncate_tool_result(&item.resp, max_chars)
+                } else {
+                    item.context.into()
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let bot = MCP_IMAGE_RE.replace_all(&entry.bot, "");
 
         HistoryChat {
             utext: entry.user.into(),
-            btext,
+            btext: bot.trim_end().to_string(),
+            tool_calls,
         }
     }
 }
@@ -132,12 +282,20 @@ impl From<UIChatSession> for ChatSession {
             .map(|entry| entry.into())
             .collect::<Vec<ChatEntry>>();
 
+        let builtin_tools = entry
+            .builtin_tools
+            .iter()
+            .filter(|item| item.enabled)
+            .map(|item| item.name.into())
+            .collect::<Vec<String>>();
+
         ChatSession {
             uuid: entry.uuid.into(),
             time: entry.time.into(),
             prompt: entry.prompt.into(),
             prompt_type: entry.prompt_type,
             mcp_config: entry.mcp_config.into(),
+            builtin_tools,
             histories,
         }
     }
@@ -149,7 +307,8 @@ impl From<ChatSession> for UIChatSession {
             entry
                 .histories
                 .into_iter()
-                .map(|entry| entry.into())
+                .enumerate()
+                .map(|(index, entry)| entry.migrate(index).into())
                 .collect::<VecModel<UIChatEntry>>(),
         );
 
@@ -159,6 +318,7 @@ impl From<ChatSession> for UIChatSession {
             prompt: entry.prompt.into(),
             prompt_type: entry.prompt_type,
             mcp_config: entry.mcp_config.into(),
+            builtin_tools: builtin_tools_model(&entry.builtin_tools),
             histories,
         }
     }
@@ -199,15 +359,33 @@ pub async fn get_from_db() -> Vec<UIChatSession> {
     entries
 }
 
+fn builtin_tools_model(enabled_names: &[String]) -> ModelRc<UIBuiltinTool> {
+    ModelRc::new(
+        builtin::builtin_tool_infos()
+            .into_iter()
+            .map(|item| UIBuiltinTool {
+                name: item.name.into(),
+                description: item.description.into(),
+                enabled: enabled_names.iter().any(|name| name == item.name),
+                policy: mcp_logic::builtin_tool_policy(item.name),
+            })
+            .collect::<VecModel<UIBuiltinTool>>(),
+    )
+}
+
 fn chat_session_init(ui: &AppWindow) {
     let mut session = UIChatSession::default();
     session.histories = ModelRc::new(VecModel::from(vec![]));
+    session.builtin_tools = builtin_tools_model(&[]);
     ui.global::<Store>().set_current_chat_session(session);
 }
 
 pub fn init(ui: &AppWindow) {
     chat_session_init(ui);
 
+    ui.global::<Store>()
+        .set_builtin_tools_allowed_dirs(config::builtin_tools().allowed_dirs.join(";").into());
+
     let ui_handle = ui.as_weak();
     ui.global::<Logic>().on_new_chat_session(move || {
         let ui = ui_handle.unwrap();
@@ -227,10 +405,13 @@ pub fn init(ui: &AppWindow) {
     });
 
     ui.global::<Logic>().on_stop_question(move || {
+        research::stop();
+
         tokio::spawn(async move {
             let mut cc = CHAT_CACHE.lock().unwrap();
             if let Some(cc) = cc.take() {
                 _ = cc.stop_tx.send(());
+                _ = cc.cancel_tx.send(true);
             }
         });
     });
@@ -269,7 +450,8 @@ pub fn init(ui: &AppWindow) {
             .row_data(index - 1)
             .unwrap();
 
-        ui.global::<Logic>().invoke_copy_to_clipboard(entry.bot);
+        ui.global::<Logic>()
+            .invoke_copy_to_clipboard(md::bot_text_with_links(&entry).into());
     });
 
     let ui_handle = ui.as_weak();
@@ -315,6 +497,57 @@ pub fn init(ui: &AppWindow) {
 
             toast_success!(ui, tr("Clear current session prompt successfully"));
         });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_toggle_builtin_tool(move |index| {
+        let ui = ui_handle.unwrap();
+        let index = index as usize;
+
+        let Some(mut entry) = store_current_chat_session_builtin_tools!(ui).row_data(index) else {
+            return;
+        };
+        entry.enabled = !entry.enabled;
+        store_current_chat_session_builtin_tools!(ui).set_row_data(index, entry);
+
+        if store_current_chat_session_histories!(ui).row_count() > 0 {
+            update_db_entry(&ui);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_set_builtin_tool_policy(move |index, policy| {
+            let ui = ui_handle.unwrap();
+
+            if let Some(entry) =
+                store_current_chat_session_builtin_tools!(ui).row_data(index as usize)
+            {
+                mcp_logic::set_builtin_tool_policy(&ui, &entry.name, policy);
+            }
+        });
+
+    ui.global::<Logic>()
+        .on_has_enabled_builtin_tools(|tools| tools.iter().any(|item| item.enabled));
+
+    ui.global::<Logic>()
+        .on_has_urls(|text| !search::find_urls(&text).is_empty());
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_set_builtin_tools_allowed_dirs(move |dirs| {
+            let ui = ui_handle.unwrap();
+            ui.global::<Store>()
+                .set_builtin_tools_allowed_dirs(dirs.clone());
+
+            let mut all = config::all();
+            all.builtin_tools.allowed_dirs = dirs
+                .split(';')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect();
+
+            _ = config::save(all);
+        });
 }
 
 fn parse_prompt(
@@ -476,16 +709,37 @@ async fn search_webpages(
     log::info!("start searching wabpages...");
 
     async_update_chat_phase(ui.clone(), ChatPhase::Searching);
-    let config = setting_model().into();
+    let setting = setting_model();
+
+    let search_queries = if setting.web_search.generate_queries {
+        match generate_search_queries(question, histories).await {
+            Ok(queries) => queries,
+            Err(e) => {
+                log::warn!("generate search queries failed: {e:?}");
+                vec![]
+            }
+        }
+    } else {
+        vec![]
+    };
 
-    match search::google::search(question, config).await {
+    let queries = if search_queries.is_empty() {
+        vec![question.to_string()]
+    } else {
+        log::info!("generated search queries: {search_queries:?}");
+        search_queries.clone()
+    };
+
+    let provider = search_provider(setting);
+    match provider
+        .search_queries(&queries, &RankOptions::default())
+        .await
+    {
         Ok((Some(text), search_links)) => {
             log::info!("webpages content length: {}", text.len());
             log::info!("finished searching webpages");
 
-            let text = format!(
-                "The following web contents are relevant to the user's question. Please consult these resources when preparing your answer. {text}"
-            );
+            let text = format!("{SEARCH_CONTEXT_INSTRUCTION} {text}");
 
             histories.push(HistoryChat {
                 utext: text,
@@ -508,6 +762,13 @@ async fn search_webpages(
 
                     store_current_chat_session_histories_search_links!(last_entry)
                         .set_vec(search_links);
+
+                    store_current_chat_session_histories_search_queries!(last_entry).set_vec(
+                        search_queries
+                            .into_iter()
+                            .map(|item| item.into())
+                            .collect::<Vec<SharedString>>(),
+                    );
                 }
             });
         }
@@ -525,6 +786,251 @@ async fn search_webpages(
     true
 }
 
+// Fetch the links which are pasted into the question and send their contents
+// to the model, so "summarize this" works without the web search
+async fn fetch_question_urls(
+    ui: Weak<AppWindow>,
+    urls: Vec<String>,
+    histories: &mut Vec<HistoryChat>,
+) {
+    log::info!("start fetching the links of the question: {urls:?}");
+    async_update_chat_phase(ui.clone(), ChatPhase::Searching);
+
+    let links = urls
+        .into_iter()
+        .take(QUESTION_URLS_MAX_COUNT)
+        .map(|link| search::SearchLink {
+            title: String::default(),
+            link,
+        })
+        .collect::<Vec<_>>();
+
+    let pages = search::fetch_pages(&links).await;
+    if pages.len() < links.len() {
+        let failed = links
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !pages.iter().any(|(i, _)| i == index))
+            .map(|(_, item)| item.link.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        toast::async_toast_warn(
+            ui.clone(),
+            format!("{}: {failed}", tr("Fetch links failed")),
+        );
+    }
+
+    if pages.is_empty() {
+        return;
+    }
+
+    let (mut attachments, mut contents) = (vec![], vec![]);
+    for (index, text) in pages {
+        let link = links[index].link.clone();
+        let title = page_title(&link, &text);
+
+        contents.push(serde_json::json!({
+            "url": link,
+            "contents": cutil::str::truncate_middle(&text, QUESTION_URL_MAX_CHARS, |count| {
+                format!("\n\n... [{count} characters omitted] ...\n\n")
+            }),
+        }));
+        attachments.push(UISearchLink {
+            title: title.into(),
+            link: link.into(),
+        });
+    }
+
+    histories.push(HistoryChat {
+        utext: format!(
+            "{URL_CONTEXT_INSTRUCTION} {}",
+            serde_json::Value::Array(contents)
+        ),
+        ..Default::default()
+    });
+
+    _ = slint::invoke_from_event_loop(move || {
+        let ui = ui.unwrap();
+
+        let rows = store_current_chat_session_histories!(ui).row_count();
+        if rows > 0 {
+            let last_entry = store_current_chat_session_histories!(ui)
+                .row_data(rows - 1)
+                .unwrap();
+
+            store_current_chat_session_histories_url_attachments!(last_entry).set_vec(attachments);
+        }
+    });
+}
+
+// The first heading of the page, or the link without its scheme
+fn page_title(link: &str, text: &str) -> String {
+    let title = text
+        .lines()
+        .next()
+        .filter(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim())
+        .filter(|line| !line.is_empty())
+        .unwrap_or_else(|| link.split_once("://").map(|item| item.1).unwrap_or(link));
+
+    cutil::str::truncate_middle(title, URL_ATTACHMENT_TITLE_MAX_CHARS, |_| "...".to_string())
+}
+
+async fn deep_research(
+    ui: Weak<AppWindow>,
+    question: &str,
+    histories: &mut Vec<HistoryChat>,
+) -> bool {
+    log::info!("start deep research...");
+    async_update_chat_phase(ui.clone(), ChatPhase::Researching);
+
+    match research::run(ui.clone(), question, histories).await {
+        Ok(Some(research)) => {
+            log::info!(
+                "finished deep research with {} links and {} notes",
+                research.links.len(),
+                research.notes.len()
+            );
+
+            if let Some(text) = research.context {
+                histories.push(HistoryChat {
+                    utext: text,
+                    ..Default::default()
+                });
+            }
+
+            _ = slint::invoke_from_event_loop(move || {
+                let ui = ui.unwrap();
+
+                let rows = store_current_chat_session_histories!(ui).row_count();
+                if rows > 0 {
+                    let last_entry = store_current_chat_session_histories!(ui)
+                        .row_data(rows - 1)
+                        .unwrap();
+
+                    store_current_chat_session_histories_search_links!(last_entry).set_vec(
+                        research
+                            .links
+                            .into_iter()
+                            .map(|item| item.into())
+                            .collect::<Vec<UISearchLink>>(),
+                    );
+
+                    store_current_chat_session_histories_research_notes!(last_entry).set_vec(
+                        research
+                            .notes
+                            .into_iter()
+                            .map(|item| item.into())
+                            .collect::<Vec<SharedString>>(),
+                    );
+                }
+            });
+
+            true
+        }
+        Ok(None) => {
+            log::info!("deep research is stopped");
+            false
+        }
+        Err(e) => {
+            async_update_chat_phase(ui.clone(), ChatPhase::None);
+            toast::async_toast_warn(
+                ui.clone(),
+                format!("{}. {}: {e:?}", tr("Deep research failed"), tr("Reason")),
+            );
+            false
+        }
+    }
+}
+
+// The web contents of the entry are replayed before it, so the follow-ups
+// are still grounded on the same sources
+fn entry_histories(entry: UIChatEntry, replay_web_context: bool) -> Vec<HistoryChat> {
+    let mut histories = vec![];
+    if replay_web_context && !entry.web_context.is_empty() {
+        histories.push(HistoryChat {
+            utext: format!(
+                "{REPLAY_CONTEXT_INSTRUCTION}\n\n{}",
+                strip_context_instruction(&entry.web_context)
+            ),
+            ..Default::default()
+        });
+    }
+
+    histories.push(entry.into());
+    histories
+}
+
+// Only the web contents of the latest entries within the budget are replayed
+fn session_histories(entries: Vec<UIChatEntry>) -> Vec<HistoryChat> {
+    let mut budget = REPLAY_WEB_CONTEXT_MAX_CHARS;
+    let mut histories = entries
+        .into_iter()
+        .rev()
+        .flat_map(|entry| {
+            let size = entry.web_context.chars().count();
+            let replay = size <= budget;
+            budget = if replay { budget - size } else { 0 };
+
+            entry_histories(entry, replay).into_iter().rev()
+        })
+        .collect::<Vec<_>>();
+
+    histories.reverse();
+    histories
+}
+
+// The instructions are only for the question which the contents are sent with,
+// so they are replaced with the labels of the contents. The entries which were
+// kept with the instructions are also stripped when they are replayed.
+fn strip_context_instruction(text: &str) -> String {
+    [
+        (SEARCH_CONTEXT_INSTRUCTION, "The web contents:"),
+        (
+            URL_CONTEXT_INSTRUCTION,
+            "The contents of the links in the question:",
+        ),
+        (research::REPORT_INSTRUCTION, "The web research:"),
+    ]
+    .into_iter()
+    .find_map(|(instruction, label)| {
+        text.strip_prefix(instruction)
+            .map(|contents| format!("{label} {}", contents.trim()))
+    })
+    .unwrap_or_else(|| text.to_string())
+}
+
+fn async_set_entry_web_context(ui: Weak<AppWindow>, contexts: &[HistoryChat]) {
+    if contexts.is_empty() {
+        return;
+    }
+
+    let context = contexts
+        .iter()
+        .map(|item| strip_context_instruction(&item.utext))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let context = cutil::str::truncate_middle(&context, ENTRY_WEB_CONTEXT_MAX_CHARS, |count| {
+        format!("\n\n... [{count} characters omitted] ...\n\n")
+    });
+
+    _ = slint::invoke_from_event_loop(move || {
+        let ui = ui.unwrap();
+
+        let rows = store_current_chat_session_histories!(ui).row_count();
+        if rows > 0 {
+            let mut entry = store_current_chat_session_histories!(ui)
+                .row_data(rows - 1)
+                .unwrap();
+
+            entry.web_context = context.into();
+            store_current_chat_session_histories!(ui).set_row_data(rows - 1, entry);
+        }
+    });
+}
+
 fn chat_histories(ui: &AppWindow, question: SharedString) -> Vec<HistoryChat> {
     let mut session = store_current_chat_session!(ui);
     let (is_new_chat, histories) = if session.uuid.is_empty() {
@@ -534,11 +1040,7 @@ fn chat_histories(ui: &AppWindow, question: SharedString) -> Vec<HistoryChat> {
 
         (true, vec![])
     } else {
-        let histories = session
-            .histories
-            .iter()
-            .map(|entry| entry.into())
-            .collect::<Vec<HistoryChat>>();
+        let histories = session_histories(session.histories.iter().collect());
 
         (false, histories)
     };
@@ -548,6 +1050,9 @@ fn chat_histories(ui: &AppWindow, question: SharedString) -> Vec<HistoryChat> {
         md_elems: ModelRc::new(VecModel::from(vec![])),
         link_urls: ModelRc::new(VecModel::from(vec![])),
         search_links: ModelRc::new(VecModel::from(vec![])),
+        search_queries: ModelRc::new(VecModel::from(vec![])),
+        research_notes: ModelRc::new(VecModel::from(vec![])),
+        url_attachments: ModelRc::new(VecModel::from(vec![])),
         mcp: ModelRc::new(VecModel::from(vec![])),
         ..Default::default()
     });
@@ -559,23 +1064,69 @@ fn chat_histories(ui: &AppWindow, question: SharedString) -> Vec<HistoryChat> {
     histories
 }
 
+fn enabled_builtin_tools(ui: &AppWindow) -> Vec<String> {
+    store_current_chat_session!(ui)
+        .builtin_tools
+        .iter()
+        .filter(|item| item.enabled)
+        .map(|item| item.name.into())
+        .collect()
+}
+
+fn builtin_options() -> BuiltinOptions {
+    BuiltinOptions {
+        allowed_dirs: config::builtin_tools()
+            .allowed_dirs
+            .into_iter()
+            .map(PathBuf::from)
+            .collect(),
+        clipboard_reader: Some(clipboard::paste_from_clipboard),
+    }
+}
+
+fn builtin_chat_tools(builtin_tools: &[String]) -> ChatTools {
+    let mut tool_set = ToolSet::default();
+    builtin::add_builtin_tools(&mut tool_set, builtin_tools, &builtin_options());
+
+    ChatTools {
+        tool_set,
+        options: CallOptions::default(),
+        mcp_config: SharedString::default(),
+        _usage: None,
+    }
+}
+
 async fn create_mcp_client(
     ui: Weak<AppWindow>,
-    config: &str,
-) -> (Option<mcp::Client>, Option<String>) {
+    config: SharedString,
+    builtin_tools: &[String],
+    settings: ToolPromptSettings,
+) -> (Option<ChatTools>, Option<String>) {
     async_update_chat_phase(ui.clone(), ChatPhase::MCP);
 
-    match mcp::create_mcp_client(config).await {
-        Ok(client) => match gen_mcp_prompt(&client) {
-            Some(prompt) => {
-                async_set_current_chat_session_prompt(ui.clone(), prompt.clone().into());
-
-                return (Some(client), Some(prompt));
-            }
-            _ => {
-                toast::async_toast_warn(ui.clone(), format!("{}", tr("No MCP server tools")));
+    match mcp::create_mcp_client(&config).await {
+        Ok(client) => {
+            let mut tool_set = client.tool_set;
+            tool_set.retain(|name| settings.is_allowed(name));
+            builtin::add_builtin_tools(&mut tool_set, builtin_tools, &builtin_options());
+
+            match gen_mcp_prompt(&tool_set, &settings) {
+                Some(prompt) => {
+                    async_set_current_chat_session_prompt(ui.clone(), prompt.clone().into());
+
+                    let tools = ChatTools {
+                        tool_set,
+                        options: client.options,
+                        mcp_config: config,
+                        _usage: Some(client.usage),
+                    };
+                    return (Some(tools), Some(prompt));
+                }
+                _ => {
+                    toast::async_toast_warn(ui.clone(), format!("{}", tr("No MCP server tools")));
+                }
             }
-        },
+        }
         Err(e) => {
             toast::async_toast_warn(
                 ui.clone(),
@@ -618,6 +1169,7 @@ fn prepare_chat(
             ui: ui.clone(),
             bot_text: String::default(),
             stop_tx: Arc::new(stop_tx),
+            cancel_tx: watch::channel(false).0,
             reasoner_start: if enabled_reasoner_model {
                 Some(Utc::now())
             } else {
@@ -629,7 +1181,13 @@ fn prepare_chat(
     (chat, id)
 }
 
-async fn start_chat(ui: Weak<AppWindow>, chat: Chat, id: u64, mcp_client: Option<mcp::Client>) {
+async fn start_chat(
+    ui: Weak<AppWindow>,
+    chat: Chat,
+    id: u64,
+    chat_tools: Option<ChatTools>,
+    session_uuid: SharedString,
+) {
     match chat
         .start(id, |item| {
             stream_text(id, item);
@@ -643,8 +1201,8 @@ async fn start_chat(ui: Weak<AppWindow>, chat: Chat, id: u64, mcp_client: Option
             );
         }
         _ => {
-            if mcp_client.is_some() {
-                call_mcp_server_tool(ui.clone(), mcp_client.unwrap(), id).await;
+            if let Some(tools) = chat_tools {
+                call_mcp_server_tool(ui.clone(), tools, id, session_uuid).await;
             }
         }
     }
@@ -655,30 +1213,64 @@ async fn start_chat(ui: Weak<AppWindow>, chat: Chat, id: u64, mcp_client: Option
 fn send_question(ui: &AppWindow, question: SharedString) {
     let (mut prompt, question, temperature) = parse_prompt(ui, question);
     let mut histories = chat_histories(ui, question.clone());
+    let session_uuid = store_current_chat_session!(ui).uuid;
 
     let mcp_config = store_current_chat_session!(ui).mcp_config;
     let prompt_type = store_current_chat_session!(ui).prompt_type;
+    let tool_prompt_settings = mcp_logic::tool_prompt_settings(ui, &mcp_config);
+    let builtin_tools = enabled_builtin_tools(ui);
 
     let enabled_reasoner_model = ui.global::<Store>().get_enabled_reasoner_model();
     let enabled_search_webpages = ui.global::<Store>().get_enabled_search_webpages();
+    let enabled_deep_research = ui.global::<Store>().get_enabled_deep_research();
+    let question_urls = if ui.global::<Store>().get_enabled_fetch_urls() {
+        search::find_urls(&question)
+    } else {
+        vec![]
+    };
 
     let ui = ui.as_weak();
     tokio::spawn(async move {
-        if enabled_search_webpages && !search_webpages(ui.clone(), &question, &mut histories).await
+        let context_start = histories.len();
+
+        if !question_urls.is_empty() {
+            fetch_question_urls(ui.clone(), question_urls, &mut histories).await;
+        }
+
+        if enabled_deep_research {
+            if !deep_research(ui.clone(), &question, &mut histories).await {
+                return;
+            }
+        } else if enabled_search_webpages
+            && !search_webpages(ui.clone(), &question, &mut histories).await
         {
             return;
         }
 
-        let mut mcp_client = None;
+        async_set_entry_web_context(ui.clone(), &histories[context_start..]);
+
+        let mut chat_tools = None;
         if !mcp_config.is_empty() && prompt_type == PromptType::MCP {
             log::info!("start create mcp client...");
-            match create_mcp_client(ui.clone(), &mcp_config).await {
-                (Some(client), Some(p)) => {
-                    mcp_client = Some(client);
+            match create_mcp_client(ui.clone(), mcp_config, &builtin_tools, tool_prompt_settings)
+                .await
+            {
+                (Some(tools), Some(p)) => {
+                    chat_tools = Some(tools);
                     prompt = p.into();
                 }
                 _ => return,
             }
+        } else if !builtin_tools.is_empty() {
+            let tools = builtin_chat_tools(&builtin_tools);
+            if let Some(p) = gen_mcp_prompt(&tools.tool_set, &ToolPromptSettings::default()) {
+                prompt = if prompt.is_empty() {
+                    p.into()
+                } else {
+                    format!("{prompt}\n\n{p}").into()
+                };
+                chat_tools = Some(tools);
+            }
         }
 
         log::info!("start sending question to model...");
@@ -691,7 +1283,7 @@ fn send_question(ui: &AppWindow, question: SharedString) {
             enabled_reasoner_model,
         );
 
-        start_chat(ui, chat, id, mcp_client).await;
+        start_chat(ui, chat, id, chat_tools, session_uuid).await;
     });
 }
 
@@ -775,29 +1367,44 @@ pub fn delete_db_entry(ui: &AppWindow, uuid: SharedString) {
     });
 }
 
-fn gen_mcp_prompt(client: &mcp::Client) -> Option<String> {
-    let tools = client.tool_set.tools();
+fn gen_mcp_prompt(tool_set: &ToolSet, settings: &ToolPromptSettings) -> Option<String> {
+    let mut tools = tool_set.tools();
     if tools.is_empty() {
         return None;
     }
+    tools.sort_by_key(|tool| tool.name());
 
-    let mut prompt =
-        "You are a assistant, you can help user to complete various tasks. You have the following tools to use:\n".to_string();
-
+    let mut tools_prompt = String::default();
     for tool in tools {
-        prompt.push_str(&format!(
+        let name = tool.name();
+        let description = settings
+            .descriptions
+            .get(&name)
+            .cloned()
+            .unwrap_or(tool.description());
+
+        tools_prompt.push_str(&format!(
             "\ntool name: {}\ndescription: {}\nparameters: {}\n",
-            tool.name(),
-            tool.description(),
+            name,
+            description,
             serde_json::to_string_pretty(&tool.parameters()).unwrap_or_default()
         ));
     }
 
+    let template = settings.template();
+    let mut prompt = if template.contains(mcp_logic::TOOL_PROMPT_TOOLS_PLACEHOLDER) {
+        template.replace(mcp_logic::TOOL_PROMPT_TOOLS_PLACEHOLDER, &tools_prompt)
+    } else {
+        format!("{template}\n{tools_prompt}")
+    };
+
     prompt.push_str(&format!(
         r#"
 Each tool calling format:
 {}
-{{"name": "tool_name", "arguments": "tool_arguments"}}
+{{"name": "tool_name", "arguments": {{"arg_name": "arg_value"}}}}
+
+The arguments must be a JSON object matching the parameters of the tool.
 {}
 "#,
         MCP_TOOL_START_SEP, MCP_TOOL_END_SEP
@@ -806,12 +1413,21 @@ Each tool calling format:
     Some(prompt)
 }
 
-async fn call_mcp_server_tool(ui: Weak<AppWindow>, client: mcp::Client, id: u64) {
+async fn call_mcp_server_tool(
+    ui: Weak<AppWindow>,
+    tools: ChatTools,
+    id: u64,
+    session_uuid: SharedString,
+) {
     let content = get_chat_cache_bot_text();
     if content.is_empty() {
         return;
     }
 
+    let Some(cancel_rx) = chat_cancel_receiver(id) else {
+        return;
+    };
+
     log::info!("start call mcp server tool...");
 
     async_update_chat_phase(ui.clone(), ChatPhase::MCP);
@@ -819,6 +1435,12 @@ async fn call_mcp_server_tool(ui: Weak<AppWindow>, client: mcp::Client, id: u64)
     let tool_list = parse_tool_list(&content);
     pretty_mcp_tool_sep(ui.clone(), tool_list.clone());
 
+    let server_name = mcp::mcp_server_name_from_config(&tools.mcp_config).unwrap_or_default();
+
+    // Ask for the permissions one by one, then run the approved calls.
+    // The responses are added in the model's original order.
+    let mut calls: Vec<(ToolCallRecord, Option<ToolCallFuture>)> = vec![];
+    let mut audits: Vec<ToolCallAuditSlot> = vec![];
     for text in &tool_list {
         if let Ok(item) = serde_json::from_str::<mcp::tool::ToolCall>(&text) {
             if item.name.is_empty() {
@@ -826,48 +1448,225 @@ async fn call_mcp_server_tool(ui: Weak<AppWindow>, client: mcp::Client, id: u64)
             }
 
             if !is_current_chat(id) {
+                cancel_tool_call_audits(&audits);
                 return;
             }
 
-            match client.tool_set.get_tool(&item.name) {
+            match tools.tool_set.get_tool(&item.name) {
                 Some(tool) => {
                     log::info!("tool: {}", item.name);
                     log::info!("tool arguments: {:?}", item.arguments);
 
-                    match tool.call(item.arguments).await {
-                        Ok(result) => {
-                            if !is_current_chat(id) {
-                                return;
-                            }
-
-                            add_mcp_tool_response(ui.clone(), item.name, result);
-                        }
+                    let mut record = ToolCallRecord {
+                        id: format!("call_{}", Uuid::new_v4().simple()),
+                        name: item.name.clone(),
+                        arguments: item.arguments.to_string(),
+                    };
+
+                    let source = if tool.is_builtin() {
+                        mcp_logic::ToolSource::Builtin
+                    } else {
+                        mcp_logic::ToolSource::Server(tools.mcp_config.clone())
+                    };
+
+                    let mut audit = mcp_logic::ToolCallAudit {
+                        session_uuid: session_uuid.to_string(),
+                        server: match source {
+                            mcp_logic::ToolSource::Builtin => BUILTIN_SERVER_NAME.to_string(),
+                            _ => server_name.clone(),
+                        },
+                        tool: item.name.clone(),
+                        arguments: item.arguments.to_string(),
+                    };
+
+                    // the invalid arguments are sent back to the model to correct itself
+                    let args = match tool.validate(item.arguments) {
+                        Ok(args) => args,
                         Err(e) => {
-                            toast::async_toast_warn(
-                                ui.clone(),
-                                format!(
-                                    "{} - {}. {}: {e:?}",
-                                    item.name,
-                                    tr("MCP server tool call failed"),
-                                    tr("Reason")
-                                ),
+                            log::warn!("tool {}: {e}", item.name);
+                            audit.record(
+                                mcp_logic::ToolCallOutcome::InvalidArguments,
+                                0,
+                                Duration::ZERO,
                             );
+                            let call: ToolCallFuture =
+                                Box::pin(async move { Some(Ok(ToolOutput::error(e.to_string()))) });
+                            calls.push((record, Some(call)));
+                            continue;
+                        }
+                    };
+
+                    audit.arguments = args.to_string();
+                    record.arguments = args.to_string();
+                    let arguments = serde_json::to_string_pretty(&args).unwrap_or(args.to_string());
+
+                    let approved = tokio::select! {
+                        approved = mcp_logic::request_tool_call_permission(
+                            ui.clone(),
+                            source,
+                            item.name.clone(),
+                            arguments,
+                        ) => approved,
+                        _ = wait_cancelled(cancel_rx.clone()) => {
+                            cancel_tool_call_audits(&audits);
+                            return;
                         }
+                    };
+
+                    if !approved {
+                        log::info!("tool call denied: {}", item.name);
+                        audit.record(mcp_logic::ToolCallOutcome::Denied, 0, Duration::ZERO);
+                        calls.push((record, None));
+                        continue;
                     }
+
+                    let audit = Arc::new(Mutex::new(Some(audit)));
+                    audits.push(audit.clone());
+
+                    let timeout = tools.options.tool_timeout(&item.name);
+                    let cancel_rx = cancel_rx.clone();
+                    let call: ToolCallFuture = Box::pin(async move {
+                        let start = Instant::now();
+                        let result = tokio::select! {
+                            result = tool.call_with_timeout(args, Some(timeout)) => Some(result),
+                            _ = wait_cancelled(cancel_rx) => None,
+                        };
+
+                        if let Some(audit) = audit.lock().unwrap().take() {
+                            audit.record_result(&result, start.elapsed());
+                        }
+                        result
+                    });
+
+                    calls.push((record, Some(call)));
                 }
                 _ => {
                     toast::async_toast_warn(
                         ui.clone(),
                         format!("{} - {}", item.name, tr("MCP server tool not found"),),
                     );
+
+                    mcp_logic::ToolCallAudit {
+                        session_uuid: session_uuid.to_string(),
+                        server: if server_name.is_empty() {
+                            BUILTIN_SERVER_NAME.to_string()
+                        } else {
+                            server_name.clone()
+                        },
+                        tool: item.name.clone(),
+                        arguments: item.arguments.to_string(),
+                    }
+                    .record(
+                        mcp_logic::ToolCallOutcome::UnknownTool,
+                        0,
+                        Duration::ZERO,
+                    );
+
+                    // the model is told the available tools to correct itself
+                    let mut names = tools
+                        .tool_set
+                        .tools()
+                        .iter()
+                        .map(|tool| tool.name())
+                        .collect::<Vec<_>>();
+                    names.sort();
+
+                    let output = ToolOutput::error(format!(
+                        "tool `{}` is not found. The available tools: {}",
+                        item.name,
+                        names.join(", ")
+                    ));
+
+                    let record = ToolCallRecord {
+                        id: format!("call_{}", Uuid::new_v4().simple()),
+                        name: item.name,
+                        arguments: item.arguments.to_string(),
+                    };
+                    let call: ToolCallFuture = Box::pin(async move { Some(Ok(output)) });
+                    calls.push((record, Some(call)));
                 }
             }
         }
     }
 
+    if tools.options.parallel {
+        calls = calls
+            .into_iter()
+            .map(|(record, call)| {
+                let call = call.map(|call| {
+                    let handle = tokio::spawn(call);
+                    Box::pin(async move { handle.await.unwrap_or(None) }) as ToolCallFuture
+                });
+                (record, call)
+            })
+            .collect();
+    }
+
+    for (record, call) in calls {
+        if !is_current_chat(id) {
+            cancel_tool_call_audits(&audits);
+            return;
+        }
+
+        let Some(call) = call else {
+            add_mcp_tool_response(
+                ui.clone(),
+                record,
+                ToolOutput::text(tr("The user denied this tool call")),
+            )
+            .await;
+            continue;
+        };
+
+        match call.await {
+            Some(Ok(result)) => {
+                if !is_current_chat(id) {
+                    cancel_tool_call_audits(&audits);
+                    return;
+                }
+
+                add_mcp_tool_response(ui.clone(), record, result).await;
+            }
+            Some(Err(e)) => {
+                if !is_current_chat(id) {
+                    cancel_tool_call_audits(&audits);
+                    return;
+                }
+
+                toast::async_toast_warn(
+                    ui.clone(),
+                    format!(
+                        "{} - {}. {}: {e:?}",
+                        record.name,
+                        tr("MCP server tool call failed"),
+                        tr("Reason")
+                    ),
+                );
+
+                // The model expects a response for each tool call id
+                add_mcp_tool_response(ui.clone(), record, ToolOutput::error(format!("{e:#}")))
+                    .await;
+            }
+            _ => {
+                log::info!("tool call cancelled: {}", record.name);
+                cancel_tool_call_audits(&audits);
+                return;
+            }
+        }
+    }
+
     async_update_db_entry(ui);
 }
 
+// The chat is stopped, so the approved calls which haven't run are recorded as cancelled
+fn cancel_tool_call_audits(audits: &[ToolCallAuditSlot]) {
+    for audit in audits {
+        if let Some(audit) = audit.lock().unwrap().take() {
+            audit.record(mcp_logic::ToolCallOutcome::Cancelled, 0, Duration::ZERO);
+        }
+    }
+}
+
 fn pretty_mcp_tool_sep(ui: Weak<AppWindow>, tool_list: Vec<String>) {
     if tool_list.is_empty() {
         return;
@@ -904,25 +1703,27 @@ fn pretty_mcp_tool_sep(ui: Weak<AppWindow>, tool_list: Vec<String>) {
     });
 }
 
-#[allow(dead_code)]
-fn paser_mcp_response(result: &str) -> Option<String> {
-    let response_text = match serde_json::from_str::<super::mcp::MCPResponse>(result) {
-        Ok(v) => Some(
-            v.content
-                .into_iter()
-                .filter(|item| item.content_type == "text")
-                .map(|item| item.text)
-                .collect::<Vec<String>>()
-                .join("\n")
-                .to_string(),
-        ),
-        _ => None,
-    };
+async fn add_mcp_tool_response(ui: Weak<AppWindow>, record: ToolCallRecord, output: ToolOutput) {
+    let name = record.name;
+    let images = output
+        .content
+        .iter()
+        .filter_map(|item| match item {
+            ToolContent::Image { data, mime_type } => match md::save_mcp_image(data, mime_type) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    log::warn!("save {name} image failed: {e:?}");
+                    None
+                }
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
 
-    response_text
-}
+    let resp = pretty_json(output.to_text().into());
+    let context = tool_result_context(&name, &resp).await;
+    let is_error = output.is_error;
 
-fn add_mcp_tool_response(ui: Weak<AppWindow>, name: String, result: String) {
     _ = slint::invoke_from_event_loop(move || {
         let ui = ui.unwrap();
 
@@ -932,20 +1733,170 @@ fn add_mcp_tool_response(ui: Weak<AppWindow>, name: String, result: String) {
         }
 
         let last_index = rows - 1;
-        let entry = store_current_chat_session_histories!(ui)
+        let mut entry = store_current_chat_session_histories!(ui)
             .row_data(last_index)
             .unwrap();
 
+        for url in images.iter() {
+            entry.bot.push_str(&format!("\n\n![{name}]({url})"));
+        }
+
         store_current_chat_session_histories_mcp!(entry).push(UIMCPElement {
             tool_name: name.into(),
-            resp: pretty_json(result.into()),
+            resp,
+            is_error,
+            context: context.into(),
+            tool_call_id: record.id.into(),
+            arguments: record.arguments.into(),
         });
 
         store_current_chat_session_histories!(ui).set_row_data(last_index, entry);
-        md::parse_stream_bot_text(&ui);
+
+        if images.is_empty() {
+            md::parse_stream_bot_text(&ui);
+        } else {
+            md::parse_last_history_bot_text(&ui);
+        }
     });
 }
 
+fn truncate_tool_result(resp: &str, max_chars: usize) -> String {
+    if max_chars == 0 {
+        return resp.to_string();
+    }
+
+    cutil::str::truncate_middle(resp, max_chars, |count| {
+        format!("\n\n... [{count} characters omitted] ...\n\n")
+    })
+}
+
+// Return the text which is resent to the model instead of the oversized result,
+// or an empty string if the result is small enough
+async fn tool_result_context(name: &str, resp: &str) -> String {
+    let setting = setting_model().tool_result;
+    let max_chars = setting.max_chars as usize;
+
+    if max_chars == 0 || resp.chars().count() <= max_chars {
+        return String::default();
+    }
+
+    if setting.summarize {
+        match summarize_tool_result(name, resp).await {
+            Ok(summary) => return truncate_tool_result(&summary, max_chars),
+            Err(e) => log::warn!("summarize {name} result failed: {e:?}"),
+        }
+    }
+
+    truncate_tool_result(resp, max_chars)
+}
+
+async fn summarize_tool_result(name: &str, resp: &str) -> anyhow::Result<String> {
+    let prompt = "You summarize the output of a tool. Keep the facts, numbers, names, paths and links which may be needed to answer the user. Reply with the summary only.";
+    let question = format!(
+        "The output of the tool `{name}`:\n\n{}",
+        truncate_tool_result(resp, SUMMARIZE_INPUT_MAX_CHARS)
+    );
+
+    let summary = complete_text(prompt, question).await?;
+
+    Ok(format!(
+        "[Summary of the {} characters output]\n{}",
+        resp.chars().count(),
+        summary
+    ))
+}
+
+// Send a single question to the chat model and return the whole answer
+pub async fn complete_text(prompt: &str, question: String) -> anyhow::Result<String> {
+    let (chat, _stop_tx) = Chat::new(prompt, question, setting_model().into(), vec![]);
+    let answer = Mutex::new(String::default());
+    let error = Mutex::new(None);
+
+    chat.start(0, |item| {
+        if let Some(text) = item.text {
+            answer.lock().unwrap().push_str(&text);
+        }
+
+        if let Some(etext) = item.etext {
+            *error.lock().unwrap() = Some(etext);
+        }
+    })
+    .await?;
+
+    if let Some(e) = error.into_inner().unwrap() {
+        anyhow::bail!(e);
+    }
+
+    let answer = answer.into_inner().unwrap();
+    if answer.trim().is_empty() {
+        anyhow::bail!("empty answer");
+    }
+
+    Ok(answer.trim().to_string())
+}
+
+// Rewrite the question and the recent turns into standalone search queries,
+// so a follow-up like "and what about the second one?" can still be searched
+async fn generate_search_queries(
+    question: &str,
+    histories: &[HistoryChat],
+) -> anyhow::Result<Vec<String>> {
+    let prompt = format!("You write web search queries. Rewrite the latest question of the user into 1 to {SEARCH_QUERIES_MAX_COUNT} standalone search queries, resolving its references to the conversation. Use the language of the question. Reply with one query per line, without numbering or any other text.");
+
+    let answer = complete_text(&prompt, question_with_conversation(question, histories)).await?;
+    Ok(parse_search_queries(&answer))
+}
+
+// The question with the recent turns of the conversation, which are truncated
+pub fn question_with_conversation(question: &str, histories: &[HistoryChat]) -> String {
+    let turns = histories
+        .iter()
+        .filter(|item| !item.btext.is_empty())
+        .collect::<Vec<_>>();
+
+    let conversation = turns[turns.len().saturating_sub(SEARCH_QUERIES_HISTORY_TURNS)..]
+        .iter()
+        .map(|item| {
+            let truncate = |text: &str| {
+                cutil::str::truncate_middle(text, SEARCH_QUERIES_HISTORY_MAX_CHARS, |_| {
+                    " ... ".to_string()
+                })
+            };
+            format!(
+                "User: {}\nAssistant: {}",
+                truncate(&item.utext),
+                truncate(&item.btext)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    if conversation.is_empty() {
+        format!("The latest question: {question}")
+    } else {
+        format!("The conversation:\n{conversation}\n\nThe latest question: {question}")
+    }
+}
+
+pub fn parse_search_queries(text: &str) -> Vec<String> {
+    let mut queries: Vec<String> = vec![];
+
+    for line in text.lines() {
+        let query = QUERY_PREFIX_RE
+            .replace(line.trim(), "")
+            .trim_matches(['"', '`'])
+            .trim()
+            .to_string();
+
+        if !query.is_empty() && !queries.iter().any(|item| item.eq_ignore_ascii_case(&query)) {
+            queries.push(query);
+        }
+    }
+
+    queries.truncate(SEARCH_QUERIES_MAX_COUNT);
+    queries
+}
+
 fn pretty_json(content: SharedString) -> SharedString {
     match serde_json::from_str::<serde_json::Value>(&content) {
         Ok(v) => serde_json::to_string_pretty(&v)
@@ -987,6 +1938,19 @@ fn is_current_chat(id: u64) -> bool {
     }
 }
 
+fn chat_cancel_receiver(id: u64) -> Option<watch::Receiver<bool>> {
+    let cc = CHAT_CACHE.lock().unwrap();
+    match cc.as_ref() {
+        Some(cc) if cc.id == id => Some(cc.cancel_tx.subscribe()),
+        _ => None,
+    }
+}
+
+// Resolve when the chat is stopped or replaced by a new one
+async fn wait_cancelled(mut cancel_rx: watch::Receiver<bool>) {
+    _ = cancel_rx.wait_for(|cancelled| *cancelled).await;
+}
+
 fn async_update_chat_phase(ui: Weak<AppWindow>, phase: ChatPhase) {
     _ = slint::invoke_from_event_loop(move || {
         ui.unwrap().global::<Store>().set_chat_phase(phase);
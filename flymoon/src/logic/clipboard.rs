@@ -31,7 +31,7 @@ fn copy_to_clipboard(msg: &str) -> Result<()> {
 }
 
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
-fn paste_from_clipboard() -> Result<String> {
+pub fn paste_from_clipboard() -> Result<String> {
     cfg_if::cfg_if! {
         if #[cfg(target_os = "linux")] {
             if super::util::is_wayland() {
@@ -63,7 +63,7 @@ fn copy_to_clipboard(msg: &str) -> Result<()> {
 }
 
 #[cfg(target_os = "android")]
-fn paste_from_clipboard() -> Result<String> {
+pub fn paste_from_clipboard() -> Result<String> {
     match terminal_clipboard::get_string() {
         Err(e) => bail!("{e:?}"),
         Ok(msg) => Ok(msg),
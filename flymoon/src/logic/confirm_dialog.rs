@@ -1,4 +1,4 @@
-use crate::slint_generatedAppWindow::{AppWindow, Logic, Util};
+use crate::slint_generatedAppWindow::{AppWindow, ConfirmDialogSetting, Logic, Util};
 use slint::ComponentHandle;
 
 pub fn init(ui: &AppWindow) {
@@ -24,6 +24,27 @@ pub fn init(ui: &AppWindow) {
                 "chat-histories-remove-selected" => {
                     ui.global::<Logic>().invoke_chat_histories_remove_selected();
                 }
+                "mcp-tool-call-approve" => {
+                    let remember = ui.global::<ConfirmDialogSetting>().get_remember();
+                    ui.global::<Logic>()
+                        .invoke_mcp_tool_call_approve(user_data, true, remember);
+                }
+                _ => (),
+            }
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Util>()
+        .on_handle_cancel_confirm_dialog(move |handle_type, user_data| {
+            let ui = ui_handle.unwrap();
+
+            #[allow(clippy::single_match)]
+            match handle_type.as_str() {
+                "mcp-tool-call-approve" => {
+                    let remember = ui.global::<ConfirmDialogSetting>().get_remember();
+                    ui.global::<Logic>()
+                        .invoke_mcp_tool_call_approve(user_data, false, remember);
+                }
                 _ => (),
             }
         });
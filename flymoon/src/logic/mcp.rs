@@ -1,32 +1,178 @@
 use super::{toast, tr::tr};
 use crate::slint_generatedAppWindow::{
-    AppWindow, Logic, MCPEntry as UIMCPEntry, MCPServerStatus as UIMCPServerStatus,
-    PromptEntry as UIPromptEntry, Store,
+    AppWindow, BuiltinTool as UIBuiltinTool, ConfirmDialogSetting, Logic,
+    MCPAuditEntry as UIMCPAuditEntry, MCPEntry as UIMCPEntry, MCPPreset as UIMCPPreset,
+    MCPPresetParam as UIMCPPresetParam, MCPServerHealth as UIMCPServerHealth,
+    MCPServerStatus as UIMCPServerStatus, MCPTool as UIMCPTool,
+    MCPToolPermission as UIMCPToolPermission, MCPToolPolicy, PromptEntry as UIPromptEntry, Store,
 };
 use crate::{
+    config,
     db::{
         self,
-        def::{MCP_TABLE, MCPEntry},
+        audit::{AuditEntry, AuditFilter},
+        def::{MCP_AUDIT_TABLE, MCP_TABLE, MCPEntry},
     },
-    store_input_prompt_list_entries, toast_success, toast_warn,
+    store_current_chat_session_builtin_tools, store_input_prompt_list_entries, toast_success,
+    toast_warn,
 };
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
-use slint::{ComponentHandle, Model, SharedString, VecModel, Weak};
+use mcp::{builtin, preset::Preset, tool::ToolOutput};
+use once_cell::sync::Lazy;
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::sync::oneshot;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MCPResponeContentItem {
-    pub text: String,
-    #[serde(rename = "type")]
-    pub content_type: String,
+// Where the tool comes from, which decides the policy of calling it
+#[derive(Debug, Clone)]
+pub enum ToolSource {
+    Builtin,
+
+    // the config of the MCP entry
+    Server(SharedString),
+}
+
+struct PendingToolCall {
+    source: ToolSource,
+    tool_name: String,
+    tx: oneshot::Sender<bool>,
+}
+
+static PENDING_TOOL_CALLS: Lazy<Mutex<HashMap<String, PendingToolCall>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Remove the pending tool call and close its confirm dialog if the request is dropped
+// before the user decides, e.g. the chat is stopped. It runs in the event loop,
+// so the pending tool call is always added before it's removed.
+struct PendingToolCallGuard {
+    ui: Weak<AppWindow>,
+    id: String,
+}
+
+impl Drop for PendingToolCallGuard {
+    fn drop(&mut self) {
+        let (ui, id) = (self.ui.clone(), std::mem::take(&mut self.id));
+
+        _ = slint::invoke_from_event_loop(move || {
+            if PENDING_TOOL_CALLS.lock().unwrap().remove(&id).is_none() {
+                return;
+            }
+
+            let ui = ui.unwrap();
+            let dialog = ui.global::<ConfirmDialogSetting>();
+            if dialog.get_handle_type() == "mcp-tool-call-approve" && dialog.get_user_data() == id {
+                dialog.invoke_hide();
+            }
+        });
+    }
+}
+
+pub const TOOL_PROMPT_TOOLS_PLACEHOLDER: &str = "{{tools}}";
+pub const DEFAULT_TOOL_PROMPT_TEMPLATE: &str = "You are a assistant, you can help user to complete various tasks. You have the following tools to use:
+{{tools}}";
+
+// The tool prompt settings of a MCP entry
+#[derive(Debug, Clone, Default)]
+pub struct ToolPromptSettings {
+    // fallback to `DEFAULT_TOOL_PROMPT_TEMPLATE`
+    pub template: String,
+
+    // `None` means the tool list is never edited, so all the tools are allowed.
+    // Otherwise the tools which are added by the server later are not allowed.
+    pub allowed_tools: Option<HashSet<String>>,
+    pub descriptions: HashMap<String, String>,
+}
+
+impl ToolPromptSettings {
+    pub fn template(&self) -> &str {
+        if self.template.trim().is_empty() {
+            DEFAULT_TOOL_PROMPT_TEMPLATE
+        } else {
+            &self.template
+        }
+    }
+
+    pub fn is_allowed(&self, name: &str) -> bool {
+        self.allowed_tools
+            .as_ref()
+            .is_none_or(|tools| tools.contains(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToolCallOutcome {
+    Success,
+    // the tool returned an error result
+    Error,
+    // the request failed or timed out
+    Failed,
+    Denied,
+    Cancelled,
+    InvalidArguments,
+    // the model called a tool which isn't provided
+    UnknownTool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MCPResponse {
-    pub content: Vec<MCPResponeContentItem>,
-    #[serde(rename = "isError")]
-    pub is_error: bool,
+impl ToolCallOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ToolCallOutcome::Success => "success",
+            ToolCallOutcome::Error => "error",
+            ToolCallOutcome::Failed => "failed",
+            ToolCallOutcome::Denied => "denied",
+            ToolCallOutcome::Cancelled => "cancelled",
+            ToolCallOutcome::InvalidArguments => "invalid",
+            ToolCallOutcome::UnknownTool => "unknown",
+        }
+    }
+}
+
+// A tool call to be recorded in the audit table
+pub struct ToolCallAudit {
+    pub session_uuid: String,
+    pub server: String,
+    pub tool: String,
+    pub arguments: String,
+}
+
+impl ToolCallAudit {
+    pub fn record(self, outcome: ToolCallOutcome, result_size: usize, duration: Duration) {
+        let entry = AuditEntry {
+            timestamp: cutil::time::local_now("%Y-%m-%d %H:%M:%S"),
+            session_uuid: self.session_uuid,
+            server: self.server,
+            tool: self.tool,
+            arguments: self.arguments,
+            result_size: result_size as i64,
+            duration_ms: duration.as_millis() as i64,
+            outcome: outcome.as_str().to_string(),
+            ..Default::default()
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = db::audit::insert(MCP_AUDIT_TABLE, &entry).await {
+                log::warn!("insert mcp audit entry failed: {e:?}");
+            }
+        });
+    }
+
+    // `None` means the call was cancelled
+    pub fn record_result(self, result: &Option<Result<ToolOutput>>, duration: Duration) {
+        let (outcome, result_size) = match result {
+            Some(Ok(output)) if output.is_error => (ToolCallOutcome::Error, output.size()),
+            Some(Ok(output)) => (ToolCallOutcome::Success, output.size()),
+            Some(Err(_)) => (ToolCallOutcome::Failed, 0),
+            None => (ToolCallOutcome::Cancelled, 0),
+        };
+
+        self.record(outcome, result_size, duration);
+    }
 }
 
 #[macro_export]
@@ -40,12 +186,11 @@ macro_rules! store_mcp_entries {
     };
 }
 
-async fn get_from_db() -> Vec<UIMCPEntry> {
+async fn get_from_db() -> Vec<MCPEntry> {
     let entries = match db::entry::select_all(MCP_TABLE).await {
         Ok(items) => items
             .into_iter()
             .filter_map(|item| serde_json::from_str::<MCPEntry>(&item.data).ok())
-            .map(|item| item.into())
             .collect(),
         Err(e) => {
             log::warn!("{:?}", e);
@@ -61,14 +206,23 @@ fn mcp_init(ui: Weak<AppWindow>) {
         let entries = get_from_db().await;
 
         let _ = slint::invoke_from_event_loop(move || {
+            let entries = entries
+                .into_iter()
+                .map(|item| item.into())
+                .collect::<Vec<UIMCPEntry>>();
+
             store_mcp_entries!(ui.unwrap()).set_vec(entries);
         });
     });
 }
 
 pub fn init(ui: &AppWindow) {
+    mcp::set_secret_resolver(config::secret);
     mcp_init(ui.as_weak());
 
+    ui.global::<Store>()
+        .set_mcp_audit_export_dir(config::exports_dir().display().to_string().into());
+
     let ui_handle = ui.as_weak();
     ui.global::<Logic>().on_mcp_update(move |mut entry| {
         let ui = ui_handle.unwrap();
@@ -137,6 +291,400 @@ pub fn init(ui: &AppWindow) {
         .on_update_all_mcp_server_status(move || {
             update_all_mcp_server_status(ui_handle.unwrap());
         });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_mcp_reset_tool_permissions(move |index| {
+            let ui = ui_handle.unwrap();
+
+            if let Some(mut entry) = store_mcp_entries!(ui).row_data(index as usize) {
+                entry.tool_permissions = ModelRc::new(VecModel::from(vec![]));
+                update_entry(&ui, entry);
+            }
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_mcp_tool_call_approve(move |id, approved, remember| {
+            let ui = ui_handle.unwrap();
+            approve_tool_call(&ui, id, approved, remember);
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_mcp_discover_tools(move |config| {
+        discover_tools(ui_handle.clone(), config);
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_mcp_presets_load(move || {
+        let ui = ui_handle.unwrap();
+        let presets = mcp::preset::load_presets(&config::mcp_presets_dir())
+            .into_iter()
+            .map(|item| item.into())
+            .collect::<Vec<UIMCPPreset>>();
+
+        ui.global::<Store>()
+            .set_mcp_presets(ModelRc::new(VecModel::from(presets)));
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_mcp_preset_select(move |id| {
+        let ui = ui_handle.unwrap();
+        let params = find_preset(&id)
+            .map(|preset| {
+                preset
+                    .params
+                    .into_iter()
+                    .map(|item| UIMCPPresetParam {
+                        name: item.name.into(),
+                        label: item.label.into(),
+                        placeholder: item.placeholder.into(),
+                        value: item.default.into(),
+                        required: item.required,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        ui.global::<Store>()
+            .set_mcp_preset_params(ModelRc::new(VecModel::from(params)));
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_mcp_preset_apply(move |id| {
+        let ui = ui_handle.unwrap();
+
+        match apply_preset(&ui, &id) {
+            Ok(entry) => entry,
+            Err(e) => {
+                toast_warn!(
+                    ui,
+                    format!(
+                        "{}. {}: {e:?}",
+                        tr("Create from preset failed"),
+                        tr("Reason")
+                    )
+                );
+                UIMCPEntry::default()
+            }
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_mcp_audit_load(move |keyword, outcome| {
+            load_audit_entries(ui_handle.clone(), audit_filter(keyword, outcome));
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_mcp_audit_export(move |keyword, outcome| {
+            export_audit_entries(ui_handle.clone(), audit_filter(keyword, outcome));
+        });
+}
+
+// Resolve the tool policy of the built-in tools or the MCP entry. Ask the user with the confirm dialog if necessary.
+pub async fn request_tool_call_permission(
+    ui: Weak<AppWindow>,
+    source: ToolSource,
+    tool_name: String,
+    arguments: String,
+) -> bool {
+    let (tx, rx) = oneshot::channel();
+    let id = Uuid::new_v4().to_string();
+    let _guard = PendingToolCallGuard {
+        ui: ui.clone(),
+        id: id.clone(),
+    };
+
+    _ = slint::invoke_from_event_loop(move || {
+        let ui = ui.unwrap();
+
+        match tool_policy(&ui, &source, &tool_name) {
+            MCPToolPolicy::Allow => _ = tx.send(true),
+            MCPToolPolicy::Deny => _ = tx.send(false),
+            MCPToolPolicy::Ask => {
+                let body = format!(
+                    "{}: {}\n\n{}: {}",
+                    tr("Tool"),
+                    tool_name,
+                    tr("Arguments"),
+                    arguments
+                );
+
+                PENDING_TOOL_CALLS.lock().unwrap().insert(
+                    id.clone(),
+                    PendingToolCall {
+                        source,
+                        tool_name,
+                        tx,
+                    },
+                );
+
+                ui.global::<ConfirmDialogSetting>()
+                    .invoke_set_with_remember(
+                        true,
+                        tr("Allow MCP tool call?").into(),
+                        body.into(),
+                        "mcp-tool-call-approve".into(),
+                        id.into(),
+                        tr("Remember my decision").into(),
+                    );
+            }
+        }
+    });
+
+    rx.await.unwrap_or(false)
+}
+
+pub fn tool_prompt_settings(ui: &AppWindow, config: &str) -> ToolPromptSettings {
+    let Some(entry) = store_mcp_entries!(ui)
+        .iter()
+        .find(|entry| entry.config.as_str() == config)
+    else {
+        return ToolPromptSettings::default();
+    };
+
+    let mut settings = ToolPromptSettings {
+        template: entry.prompt_template.into(),
+        ..Default::default()
+    };
+
+    if entry.tools.row_count() > 0 {
+        settings.allowed_tools = Some(
+            entry
+                .tools
+                .iter()
+                .filter(|tool| tool.enabled)
+                .map(|tool| tool.name.into())
+                .collect(),
+        );
+    }
+
+    for tool in entry.tools.iter() {
+        if !tool.description.trim().is_empty() {
+            settings
+                .descriptions
+                .insert(tool.name.into(), tool.description.into());
+        }
+    }
+
+    settings
+}
+
+fn discover_tools(ui: Weak<AppWindow>, config: SharedString) {
+    let server_name = match mcp::mcp_server_name_from_config(&config) {
+        Ok(name) => name,
+        Err(e) => {
+            toast::async_toast_warn(
+                ui,
+                format!("{}. {}: {e:?}", tr("Load tools failed"), tr("Reason")),
+            );
+            return;
+        }
+    };
+
+    if !mcp::mcp_server_is_running(&server_name) {
+        toast::async_toast_warn(ui, tr("Please start the MCP server first"));
+        return;
+    }
+
+    tokio::spawn(async move {
+        // the running client is reused
+        let client = match mcp::create_mcp_client(&config).await {
+            Ok(client) => client,
+            Err(e) => {
+                toast::async_toast_warn(
+                    ui,
+                    format!("{}. {}: {e:?}", tr("Load tools failed"), tr("Reason")),
+                );
+                return;
+            }
+        };
+
+        let mut tools = client
+            .tool_set
+            .tools()
+            .into_iter()
+            .map(|tool| (tool.name(), tool.description()))
+            .collect::<Vec<_>>();
+        tools.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let _ = slint::invoke_from_event_loop(move || {
+            let ui = ui.unwrap();
+            let editing_tools = ui.global::<Store>().get_mcp_editing_tools();
+
+            // keep the settings of the known tools
+            let tools = tools
+                .into_iter()
+                .map(|(name, description)| {
+                    match editing_tools.iter().find(|item| item.name.as_str() == name) {
+                        Some(item) => UIMCPTool {
+                            default_description: description.into(),
+                            ..item
+                        },
+                        _ => UIMCPTool {
+                            name: name.into(),
+                            default_description: description.into(),
+                            enabled: true,
+                            ..Default::default()
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            ui.global::<Store>()
+                .set_mcp_editing_tools(ModelRc::new(VecModel::from(tools)));
+        });
+    });
+}
+
+fn find_preset(id: &str) -> Option<Preset> {
+    mcp::preset::load_presets(&config::mcp_presets_dir())
+        .into_iter()
+        .find(|item| item.id == id)
+}
+
+fn apply_preset(ui: &AppWindow, id: &str) -> Result<UIMCPEntry> {
+    let Some(preset) = find_preset(id) else {
+        anyhow::bail!("preset `{id}` not found");
+    };
+
+    let values = ui
+        .global::<Store>()
+        .get_mcp_preset_params()
+        .iter()
+        .map(|item| (item.name.to_string(), item.value.to_string()))
+        .collect::<HashMap<_, _>>();
+
+    Ok(UIMCPEntry {
+        name: preset.name.clone().into(),
+        shortcut: preset.shortcut.clone().into(),
+        config: preset.render(&values)?.into(),
+        temperature: 0.7,
+        tool_permissions: ModelRc::new(VecModel::default()),
+        tools: ModelRc::new(VecModel::default()),
+        ..Default::default()
+    })
+}
+
+impl From<Preset> for UIMCPPreset {
+    fn from(entry: Preset) -> Self {
+        UIMCPPreset {
+            id: entry.id.into(),
+            name: entry.name.into(),
+            shortcut: entry.shortcut.into(),
+            description: entry.description.into(),
+        }
+    }
+}
+
+pub fn builtin_tool_policy(tool_name: &str) -> MCPToolPolicy {
+    match config::builtin_tools()
+        .policies
+        .get(tool_name)
+        .map(|item| item.as_str())
+    {
+        Some("Ask") => MCPToolPolicy::Ask,
+        Some("Allow") => MCPToolPolicy::Allow,
+        Some("Deny") => MCPToolPolicy::Deny,
+        _ if builtin::LOCAL_DATA_TOOLS.contains(&tool_name) => MCPToolPolicy::Ask,
+        _ => MCPToolPolicy::Allow,
+    }
+}
+
+// The policies of the built-in tools are shared by all the sessions
+pub fn set_builtin_tool_policy(ui: &AppWindow, tool_name: &str, policy: MCPToolPolicy) {
+    let mut all = config::all();
+    all.builtin_tools.policies.insert(
+        tool_name.to_string(),
+        match policy {
+            MCPToolPolicy::Ask => "Ask",
+            MCPToolPolicy::Allow => "Allow",
+            MCPToolPolicy::Deny => "Deny",
+        }
+        .to_string(),
+    );
+    _ = config::save(all);
+
+    for (index, mut entry) in store_current_chat_session_builtin_tools!(ui)
+        .iter()
+        .enumerate()
+    {
+        if entry.name.as_str() == tool_name {
+            entry.policy = policy;
+            store_current_chat_session_builtin_tools!(ui).set_row_data(index, entry);
+        }
+    }
+}
+
+fn tool_policy(ui: &AppWindow, source: &ToolSource, tool_name: &str) -> MCPToolPolicy {
+    let config = match source {
+        ToolSource::Builtin => return builtin_tool_policy(tool_name),
+        ToolSource::Server(config) => config,
+    };
+
+    match store_mcp_entries!(ui)
+        .iter()
+        .find(|entry| entry.config == *config)
+    {
+        Some(entry) => entry
+            .tool_permissions
+            .iter()
+            .find(|item| item.tool_name.as_str() == tool_name)
+            .map(|item| item.policy)
+            .unwrap_or(entry.policy),
+        _ => MCPToolPolicy::Ask,
+    }
+}
+
+fn approve_tool_call(ui: &AppWindow, id: SharedString, approved: bool, remember: bool) {
+    let Some(pending) = PENDING_TOOL_CALLS.lock().unwrap().remove(id.as_str()) else {
+        return;
+    };
+
+    if remember {
+        remember_tool_permission(ui, &pending, approved);
+    }
+
+    _ = pending.tx.send(approved);
+}
+
+fn remember_tool_permission(ui: &AppWindow, pending: &PendingToolCall, approved: bool) {
+    let policy = if approved {
+        MCPToolPolicy::Allow
+    } else {
+        MCPToolPolicy::Deny
+    };
+
+    let config = match &pending.source {
+        ToolSource::Builtin => {
+            set_builtin_tool_policy(ui, &pending.tool_name, policy);
+            return;
+        }
+        ToolSource::Server(config) => config,
+    };
+
+    let Some(mut entry) = store_mcp_entries!(ui)
+        .iter()
+        .find(|entry| entry.config == *config)
+    else {
+        return;
+    };
+
+    let mut permissions = entry
+        .tool_permissions
+        .iter()
+        .filter(|item| item.tool_name.as_str() != pending.tool_name)
+        .collect::<Vec<UIMCPToolPermission>>();
+
+    permissions.push(UIMCPToolPermission {
+        tool_name: pending.tool_name.clone().into(),
+        policy,
+    });
+
+    entry.tool_permissions = ModelRc::new(VecModel::from(permissions));
+    update_entry(ui, entry);
 }
 
 fn add_entry(ui: &AppWindow, entry_ui: UIMCPEntry) {
@@ -316,13 +864,27 @@ fn restart_mcp_client(ui: Weak<AppWindow>, index: usize) {
 
 fn update_all_mcp_server_status(ui: AppWindow) {
     for (index, mut entry) in store_mcp_entries!(ui).iter().enumerate() {
+        // starting or restarting
+        if entry.status == UIMCPServerStatus::Loading {
+            continue;
+        }
+
         match mcp::mcp_server_name_from_config(&entry.config) {
             Ok(server_name) => {
+                let health = mcp::mcp_server_health(&server_name);
+
                 entry.status = if mcp::mcp_server_is_running(&server_name) {
                     UIMCPServerStatus::Running
+                } else if health
+                    .as_ref()
+                    .is_some_and(|item| item.last_error.is_some())
+                {
+                    UIMCPServerStatus::Failed
                 } else {
                     UIMCPServerStatus::None
                 };
+
+                entry.health = server_health(&server_name, health);
             }
             _ => {
                 entry.status = UIMCPServerStatus::Failed;
@@ -333,6 +895,45 @@ fn update_all_mcp_server_status(ui: AppWindow) {
     }
 }
 
+fn server_health(server_name: &str, health: Option<mcp::ServerHealth>) -> UIMCPServerHealth {
+    let stderr = mcp::mcp_server_stderr(server_name).join("\n");
+
+    match health {
+        Some(health) => UIMCPServerHealth {
+            uptime: health
+                .uptime
+                .map(|item| format_duration(item.as_secs()))
+                .unwrap_or_default()
+                .into(),
+            last_ping: health
+                .last_ping
+                .map(|item| format!("{} {}", format_duration(item.as_secs()), tr("ago")))
+                .unwrap_or("-".to_string())
+                .into(),
+            tool_count: health.tool_count as i32,
+            restarts: health.restarts as i32,
+            last_error: health.last_error.unwrap_or_default().into(),
+            stderr: stderr.into(),
+        },
+        _ => UIMCPServerHealth {
+            stderr: stderr.into(),
+            ..Default::default()
+        },
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+
+    if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
 async fn stop_mcp_client_inner(config: &str) -> Result<()> {
     let server_name = mcp::mcp_server_name_from_config(config)?;
     mcp::cancel_mcp_client(&server_name).await?;
@@ -345,3 +946,89 @@ async fn restart_mcp_client_inner(config: &str) -> Result<()> {
     mcp::create_mcp_client(config).await?;
     Ok(())
 }
+
+fn audit_filter(keyword: SharedString, outcome: SharedString) -> AuditFilter {
+    AuditFilter {
+        keyword: keyword.trim().to_string(),
+        outcome: outcome.into(),
+        ..Default::default()
+    }
+}
+
+fn load_audit_entries(ui: Weak<AppWindow>, filter: AuditFilter) {
+    tokio::spawn(async move {
+        match db::audit::select(MCP_AUDIT_TABLE, &filter).await {
+            Ok(entries) => {
+                let _ = slint::invoke_from_event_loop(move || {
+                    let entries = entries
+                        .into_iter()
+                        .map(|item| item.into())
+                        .collect::<Vec<UIMCPAuditEntry>>();
+
+                    ui.unwrap()
+                        .global::<Store>()
+                        .set_mcp_audit_entries(ModelRc::new(VecModel::from(entries)));
+                });
+            }
+            Err(e) => toast::async_toast_warn(
+                ui,
+                format!("{}. {}: {e:?}", tr("Load audit log failed"), tr("Reason")),
+            ),
+        }
+    });
+}
+
+fn export_audit_entries(ui: Weak<AppWindow>, filter: AuditFilter) {
+    tokio::spawn(async move {
+        let path = config::exports_dir().join(format!(
+            "mcp-audit-{}.csv",
+            cutil::time::local_now("%Y%m%d-%H%M%S")
+        ));
+
+        match export_audit_entries_inner(&path, filter).await {
+            Err(e) => toast::async_toast_warn(
+                ui,
+                format!("{}. {}: {e:?}", tr("Export failed"), tr("Reason")),
+            ),
+            _ => toast::async_toast_success(
+                ui,
+                format!("{}: {}", tr("Export successfully"), path.display()),
+            ),
+        }
+    });
+}
+
+async fn export_audit_entries_inner(path: &Path, mut filter: AuditFilter) -> Result<()> {
+    // all the matched entries
+    filter.limit = Some(i64::MAX);
+    let entries = db::audit::select(MCP_AUDIT_TABLE, &filter).await?;
+
+    let mut csv = "timestamp,session_uuid,server,tool,arguments,result_size,duration_ms,outcome\n"
+        .to_string();
+    for entry in entries {
+        let fields = [
+            entry.timestamp,
+            entry.session_uuid,
+            entry.server,
+            entry.tool,
+            entry.arguments,
+            entry.result_size.to_string(),
+            entry.duration_ms.to_string(),
+            entry.outcome,
+        ];
+
+        csv.push_str(&fields.map(|item| csv_field(&item)).join(","));
+        csv.push('\n');
+    }
+
+    std::fs::write(path, csv)?;
+    Ok(())
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
@@ -5,14 +5,19 @@ use crate::slint_generatedAppWindow::{
     MdUrl as UIMdUrl, Store,
 };
 use crate::{config::cache_dir, store_current_chat_session_histories};
+use anyhow::Result;
 use cutil::{crypto, http};
 use dummy_markdown::{
-    self, MdCodeBlock, MdElement, MdElementType, MdHeading, MdListItem, MdTable, MdUrl,
+    self, citation, MdCodeBlock, MdElement, MdElementType, MdHeading, MdListItem, MdTable, MdUrl,
 };
 use once_cell::sync::Lazy;
 use once_cell::sync::OnceCell;
 use slint::{ComponentHandle, Image, Model, ModelRc, SharedString, VecModel, Weak};
 use std::{collections::HashMap, sync::Mutex};
+use uuid::Uuid;
+
+// The images returned by MCP tools are saved in the cache directory
+pub const MCP_IMAGE_SCHEME: &str = "mcp-image://";
 
 struct DownloadImageCache {
     try_times: u32,
@@ -54,6 +59,7 @@ impl From<MdElementType> for UIMdElementType {
             MdElementType::Heading => UIMdElementType::Heading,
             MdElementType::CodeBlock => UIMdElementType::CodeBlock,
             MdElementType::Table => UIMdElementType::Table,
+            MdElementType::Link => UIMdElementType::Link,
             _ => unreachable!(),
         }
     }
@@ -151,6 +157,7 @@ impl From<MdElement> for UIMdElement {
             list_item: entry.list_item.into(),
             img: entry.image_url.into(),
             heading: entry.heading.into(),
+            link: entry.link.into(),
             table: entry.table.into(),
         }
     }
@@ -165,6 +172,13 @@ pub fn init(ui: &AppWindow) {
 
             let ui = ui_handle.clone();
             tokio::spawn(async move {
+                if url.starts_with(MCP_IMAGE_SCHEME) {
+                    if let Some(file_path) = mcp_image_path(&url) {
+                        async_load_image(ui, histories_entry_index, index, url, file_path);
+                    }
+                    return;
+                }
+
                 let file_path = cache_dir().join(&format!("{}.png", crypto::hash(&url)));
 
                 if let Ok(true) = std::fs::exists(&file_path) {
@@ -234,6 +248,33 @@ pub fn init(ui: &AppWindow) {
         });
 }
 
+// Return the markdown url of the saved image
+pub fn save_mcp_image(data: &[u8], mime_type: &str) -> Result<String> {
+    let ext = match mime_type {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => "png",
+    };
+
+    let name = format!("mcp-image-{}.{ext}", Uuid::new_v4());
+    std::fs::write(cache_dir().join(&name), data)?;
+
+    Ok(format!("{MCP_IMAGE_SCHEME}{name}"))
+}
+
+fn mcp_image_path(url: &str) -> Option<std::path::PathBuf> {
+    let name = url.strip_prefix(MCP_IMAGE_SCHEME)?;
+
+    // the url comes from the bot text, so don't allow to escape the cache directory
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return None;
+    }
+
+    Some(cache_dir().join(name))
+}
+
 #[allow(dead_code)]
 pub fn need_parse_stream_bot_text(ui: &AppWindow) -> bool {
     let rows = store_current_chat_session_histories!(ui).row_count();
@@ -262,7 +303,9 @@ pub fn parse_stream_bot_text(ui: &AppWindow) {
         return;
     }
 
-    let (md_elems, _) = dummy_markdown::parser::run(bot_text, can_parse_math());
+    let sources = entry_sources(&last_entry);
+    let (md_elems, _) =
+        dummy_markdown::parser::run_with_sources(bot_text, can_parse_math(), &sources);
 
     // update Markdown elements
     let rows = store_current_chat_session_histories_md_elems!(last_entry).row_count();
@@ -332,18 +375,19 @@ pub fn parse_last_history_bot_text(ui: &AppWindow) {
         return;
     }
 
-    let (md_elems, link_urls) = dummy_markdown::parser::run(&entry.bot, can_parse_math());
-
-    let elems = md_elems
+    let sources = entry_sources(&entry);
+    let (md_elems, link_urls) =
+        dummy_markdown::parser::run_with_sources(&entry.bot, can_parse_math(), &sources);
+    let urls = link_urls
         .into_iter()
         .map(|item| item.into())
         .collect::<Vec<_>>();
-    store_current_chat_session_histories_md_elems!(entry).set_vec(elems);
 
-    let urls = link_urls
+    let elems = md_elems
         .into_iter()
         .map(|item| item.into())
         .collect::<Vec<_>>();
+    store_current_chat_session_histories_md_elems!(entry).set_vec(elems);
     store_current_chat_session_histories_link_urls!(entry).set_vec(urls);
 }
 
@@ -353,22 +397,40 @@ pub fn parse_histories_bot_text(ui: &AppWindow) {
             continue;
         }
 
-        let (md_elems, link_urls) = dummy_markdown::parser::run(&entry.bot, can_parse_math());
-
-        let elems = md_elems
+        let sources = entry_sources(&entry);
+        let (md_elems, link_urls) =
+            dummy_markdown::parser::run_with_sources(&entry.bot, can_parse_math(), &sources);
+        let urls = link_urls
             .into_iter()
             .map(|item| item.into())
             .collect::<Vec<_>>();
-        store_current_chat_session_histories_md_elems!(entry).set_vec(elems);
 
-        let urls = link_urls
+        let elems = md_elems
             .into_iter()
             .map(|item| item.into())
             .collect::<Vec<_>>();
+        store_current_chat_session_histories_md_elems!(entry).set_vec(elems);
         store_current_chat_session_histories_link_urls!(entry).set_vec(urls);
     }
 }
 
+// The sources of the `[n]` citation markers in the bot text
+fn entry_sources(entry: &UIChatEntry) -> Vec<MdUrl> {
+    entry
+        .search_links
+        .iter()
+        .map(|item| MdUrl {
+            text: item.title.into(),
+            url: item.link.into(),
+        })
+        .collect()
+}
+
+// The bot text whose citation markers are the markdown links to the sources
+pub fn bot_text_with_links(entry: &UIChatEntry) -> String {
+    citation::to_links(&entry.bot, &entry_sources(entry))
+}
+
 fn get_md_entry(ui: &AppWindow, histories_entry_index: usize, index: usize) -> Option<UIMdElement> {
     if let Some(entry) = store_current_chat_session_histories!(ui).row_data(histories_entry_index) {
         if let Some(item) = store_current_chat_session_histories_md_elems!(entry).row_data(index) {
@@ -54,6 +54,12 @@ mod chat_session;
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
 mod md;
 
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+mod cache;
+
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+mod research;
+
 pub fn init(ui: &AppWindow) {
     #[cfg(any(
         target_os = "windows",
@@ -79,5 +85,6 @@ pub fn init(ui: &AppWindow) {
         chat_history::init(ui);
         chat_session::init(ui);
         md::init(ui);
+        cache::init(ui);
     }
 }
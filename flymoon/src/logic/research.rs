@@ -0,0 +1,351 @@
+// The deep research: plan the sub-questions, search and read the pages, note
+// what is still missing and search again, until the budget of the steps or the
+// pages is used up. The final report is written by the chat with the notes.
+use super::{
+    chat_session::{
+        complete_text, parse_search_queries, question_with_conversation, search_provider,
+    },
+    tr::tr,
+};
+use crate::{
+    config::model as setting_model,
+    slint_generatedAppWindow::{AppWindow, Store},
+};
+use anyhow::Result;
+use bot::openai::request::HistoryChat;
+use once_cell::sync::Lazy;
+use search::{
+    rank::{self, RankOptions},
+    SearchLink,
+};
+use slint::{ComponentHandle, Weak};
+use std::{future::Future, sync::Mutex};
+use tokio::sync::watch;
+
+// The chunks which are given to the final report
+const REPORT_TOP_K: usize = 16;
+const REPORT_MAX_TOKENS: usize = 6000;
+
+pub const REPORT_INSTRUCTION: &str = "The following are the notes of a web research on the user's question and the relevant parts of the web pages. Each part is tagged with the number of its source. Write a well-structured report which answers the question. Cite the sources with their numbers in square brackets right after the statements they support, e.g. [1] or [1][3].";
+
+// The cancel sender of the running research. Each research has its own channel,
+// so a stopped research can't be resumed by a new one.
+static CANCEL_TX: Lazy<Mutex<Option<watch::Sender<bool>>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Default)]
+pub struct Research {
+    // the notes and the ranked chunks for writing the report
+    pub context: Option<String>,
+
+    pub links: Vec<SearchLink>,
+
+    // the notes of each step
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Reflection {
+    notes: String,
+    missing: String,
+    queries: Vec<String>,
+}
+
+pub fn stop() {
+    if let Some(cancel_tx) = CANCEL_TX.lock().unwrap().take() {
+        _ = cancel_tx.send(true);
+    }
+}
+
+// The previous research is stopped because its sender is dropped
+fn cancel_receiver() -> watch::Receiver<bool> {
+    let (cancel_tx, cancel_rx) = watch::channel(false);
+    *CANCEL_TX.lock().unwrap() = Some(cancel_tx);
+    cancel_rx
+}
+
+fn is_stopped(cancel_rx: &watch::Receiver<bool>) -> bool {
+    *cancel_rx.borrow() || cancel_rx.has_changed().is_err()
+}
+
+// Return `None` if the research is stopped before the future is done
+async fn until_stopped<T>(
+    cancel_rx: &mut watch::Receiver<bool>,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    tokio::select! {
+        output = future => Some(output),
+        _ = cancel_rx.wait_for(|cancelled| *cancelled) => None,
+    }
+}
+
+fn update_progress(ui: Weak<AppWindow>, progress: String) {
+    _ = slint::invoke_from_event_loop(move || {
+        ui.unwrap()
+            .global::<Store>()
+            .set_research_progress(progress.into());
+    });
+}
+
+fn is_same_link(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+// Return `None` if the research is stopped
+pub async fn run(
+    ui: Weak<AppWindow>,
+    question: &str,
+    histories: &[HistoryChat],
+) -> Result<Option<Research>> {
+    let mut cancel_rx = cancel_receiver();
+
+    let setting = setting_model();
+    let max_steps = setting.web_search.research_max_steps.max(1);
+    let max_pages = setting.web_search.research_max_pages.max(1) as usize;
+    let provider = search_provider(setting);
+
+    update_progress(ui.clone(), tr("Planning the research..."));
+    let Some(queries) = until_stopped(&mut cancel_rx, plan(question, histories)).await else {
+        return Ok(None);
+    };
+    let mut queries = queries?;
+    let (mut research, mut pages, mut all_queries) = (Research::default(), vec![], vec![]);
+
+    for step in 1..=max_steps {
+        if is_stopped(&cancel_rx) {
+            return Ok(None);
+        }
+
+        if queries.is_empty() || research.links.len() >= max_pages {
+            break;
+        }
+
+        update_progress(
+            ui.clone(),
+            format!(
+                "{} {step}/{max_steps}. {}: {}",
+                tr("Step"),
+                tr("Searching"),
+                queries.join("; ")
+            ),
+        );
+
+        let mut found = vec![];
+        for query in queries.iter() {
+            match until_stopped(&mut cancel_rx, provider.cached_search_links(query)).await {
+                Some(Ok(links)) => found.push(links),
+                Some(Err(e)) => log::warn!("search `{query}` failed: {e:?}"),
+                None => return Ok(None),
+            }
+        }
+
+        // The pages of the research are limited by `max_pages` instead of the provider
+        let new_links = search::merge_links(found, usize::MAX)
+            .into_iter()
+            .filter(|item| {
+                !research
+                    .links
+                    .iter()
+                    .any(|link| is_same_link(&link.link, &item.link))
+            })
+            .take(max_pages - research.links.len())
+            .collect::<Vec<_>>();
+
+        if new_links.is_empty() {
+            break;
+        }
+
+        update_progress(
+            ui.clone(),
+            format!(
+                "{} {step}/{max_steps}. {}: {}",
+                tr("Step"),
+                tr("Reading pages"),
+                new_links.len()
+            ),
+        );
+
+        let offset = research.links.len();
+        research.links.extend(new_links.iter().cloned());
+
+        let Some(new_pages) = until_stopped(&mut cancel_rx, search::fetch_pages(&new_links)).await
+        else {
+            return Ok(None);
+        };
+
+        let new_pages = new_pages
+            .into_iter()
+            .map(|(index, text)| (index + offset, text))
+            .collect::<Vec<_>>();
+
+        let chunks = rank::top_chunks(&queries.join(" "), &new_pages, &RankOptions::default());
+        pages.extend(new_pages);
+        all_queries.append(&mut queries);
+
+        let Some(context) = search::web_context(&research.links, chunks) else {
+            continue;
+        };
+
+        update_progress(
+            ui.clone(),
+            format!("{} {step}/{max_steps}. {}", tr("Step"), tr("Reflecting")),
+        );
+
+        let Some(reflection) =
+            until_stopped(&mut cancel_rx, reflect(question, &research.notes, &context)).await
+        else {
+            return Ok(None);
+        };
+        let reflection = reflection?;
+        if !reflection.notes.is_empty() {
+            research.notes.push(if reflection.missing.is_empty() {
+                reflection.notes
+            } else {
+                format!("{}\n\nMissing: {}", reflection.notes, reflection.missing)
+            });
+        }
+
+        queries = reflection
+            .queries
+            .into_iter()
+            .filter(|item| {
+                !all_queries
+                    .iter()
+                    .any(|query| query.eq_ignore_ascii_case(item))
+            })
+            .collect();
+    }
+
+    let options = RankOptions {
+        top_k: REPORT_TOP_K,
+        max_tokens: REPORT_MAX_TOKENS,
+        ..Default::default()
+    };
+    let query = format!("{question} {}", all_queries.join(" "));
+    let chunks = rank::top_chunks(&query, &pages, &options);
+
+    if let Some(web_context) = search::web_context(&research.links, chunks) {
+        let notes = research
+            .notes
+            .iter()
+            .enumerate()
+            .map(|(index, item)| format!("Step {}:\n{item}", index + 1))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        research.context = Some(format!(
+            "{REPORT_INSTRUCTION}\n\nThe notes:\n{notes}\n\nThe web contents: {web_context}"
+        ));
+    }
+
+    Ok(Some(research))
+}
+
+// Break the question into the sub-questions and return their search queries
+async fn plan(question: &str, histories: &[HistoryChat]) -> Result<Vec<String>> {
+    let prompt = "You plan a web research. Break the latest question of the user into 1 to 3 sub-questions which are needed to answer it, resolving its references to the conversation. Write a standalone search query for each sub-question in the language of the question. Reply with one query per line, without numbering or any other text.";
+
+    let answer = complete_text(prompt, question_with_conversation(question, histories)).await?;
+    let queries = parse_search_queries(&answer);
+
+    Ok(if queries.is_empty() {
+        vec![question.to_string()]
+    } else {
+        queries
+    })
+}
+
+// Note the findings of the new pages and what is still missing
+async fn reflect(question: &str, notes: &[String], context: &str) -> Result<Reflection> {
+    let prompt = "You are researching the question of the user. Read the new web contents, each part is tagged with the number of its source. Write the notes of the findings which help answer the question, and cite the sources with their numbers in square brackets, e.g. [1]. Then write what is still missing to answer the question. Reply in this format:\nNOTES:\n<the notes>\nMISSING:\n<what is still missing, or NONE>\nQUERIES:\n<at most 3 search queries for the missing information, one per line, or NONE>";
+
+    let notes = if notes.is_empty() {
+        "NONE".to_string()
+    } else {
+        notes.join("\n\n")
+    };
+
+    let question =
+        format!("The question: {question}\n\nThe previous notes:\n{notes}\n\nThe new web contents: {context}");
+
+    let answer = complete_text(prompt, question).await?;
+    Ok(parse_reflection(&answer))
+}
+
+fn parse_reflection(text: &str) -> Reflection {
+    let (mut sections, mut current) = ([String::new(), String::new(), String::new()], None);
+
+    for line in text.lines() {
+        let header = line.trim().trim_matches(['*', '#', ' ']).to_uppercase();
+        match header.as_str() {
+            "NOTES:" => current = Some(0),
+            "MISSING:" => current = Some(1),
+            "QUERIES:" => current = Some(2),
+            _ => {
+                let index = current.unwrap_or(0);
+                sections[index].push_str(line);
+                sections[index].push('\n');
+            }
+        }
+    }
+
+    let section = |index: usize| {
+        let text = sections[index].trim();
+        if text.eq_ignore_ascii_case("none") {
+            String::default()
+        } else {
+            text.to_string()
+        }
+    };
+
+    Reflection {
+        notes: section(0),
+        missing: section(1),
+        queries: parse_search_queries(&section(2)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reflection_sections() {
+        let text = "NOTES:\nTokio is the most used runtime [1].\n\n**MISSING:**\nThe benchmarks.\nQUERIES:\n1. tokio benchmark\n2. async-std benchmark";
+        assert_eq!(
+            parse_reflection(text),
+            Reflection {
+                notes: "Tokio is the most used runtime [1].".to_string(),
+                missing: "The benchmarks.".to_string(),
+                queries: vec![
+                    "tokio benchmark".to_string(),
+                    "async-std benchmark".to_string()
+                ],
+            }
+        );
+
+        let reflection = parse_reflection("The answer is 42 [2].\nMISSING:\nNONE\nQUERIES:\nNONE");
+        assert_eq!(reflection.notes, "The answer is 42 [2].");
+        assert!(reflection.missing.is_empty() && reflection.queries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stop_research() {
+        let mut first = cancel_receiver();
+        let mut second = cancel_receiver();
+
+        // the first research is replaced by the second one
+        assert!(is_stopped(&first));
+        assert!(until_stopped(&mut first, std::future::pending::<()>())
+            .await
+            .is_none());
+
+        assert!(!is_stopped(&second));
+        assert_eq!(until_stopped(&mut second, async { 1 }).await, Some(1));
+
+        stop();
+        assert!(is_stopped(&second));
+        assert!(until_stopped(&mut second, std::future::pending::<()>())
+            .await
+            .is_none());
+    }
+}
@@ -2,7 +2,8 @@ use super::tr::tr;
 use crate::{
     config,
     slint_generatedAppWindow::{
-        AppWindow, Logic, SettingChatModel, SettingGoogleSearch, SettingModel, Store, Theme,
+        AppWindow, Logic, SettingChatModel, SettingGoogleSearch, SettingModel, SettingToolResult,
+        SettingWebSearch, Store, Theme,
     },
 };
 use slint::ComponentHandle;
@@ -94,6 +95,21 @@ pub fn init(ui: &AppWindow) {
                 api_key: config.google_search.api_key.into(),
                 num: config.google_search.num,
             },
+            web_search: SettingWebSearch {
+                provider: config.web_search.provider.into(),
+                searxng_url: config.web_search.searxng_url.into(),
+                brave_api_key: config.web_search.brave_api_key.into(),
+                bing_api_key: config.web_search.bing_api_key.into(),
+                num: config.web_search.num,
+                cache_hours: slint::format!("{}", config.web_search.cache_hours),
+                generate_queries: config.web_search.generate_queries,
+                research_max_steps: slint::format!("{}", config.web_search.research_max_steps),
+                research_max_pages: slint::format!("{}", config.web_search.research_max_pages),
+            },
+            tool_result: SettingToolResult {
+                max_chars: slint::format!("{}", config.tool_result.max_chars),
+                summarize: config.tool_result.summarize,
+            },
         }
     });
 
@@ -103,9 +119,6 @@ pub fn init(ui: &AppWindow) {
         ui.global::<Store>()
             .set_reasoner_model_available(!setting.chat.reasoner_model_name.trim().is_empty());
 
-        ui.global::<Store>()
-            .set_search_webpages_available(!setting.google_search.api_key.trim().is_empty());
-
         let mut all = config::all();
 
         all.model.chat = config::data::ChatModel {
@@ -121,7 +134,50 @@ pub fn init(ui: &AppWindow) {
             num: setting.google_search.num,
         };
 
+        all.model.web_search = config::data::WebSearch {
+            provider: setting.web_search.provider.into(),
+            searxng_url: setting.web_search.searxng_url.into(),
+            brave_api_key: setting.web_search.brave_api_key.into(),
+            bing_api_key: setting.web_search.bing_api_key.into(),
+            num: setting.web_search.num,
+            cache_hours: setting
+                .web_search
+                .cache_hours
+                .trim()
+                .parse()
+                .unwrap_or(all.model.web_search.cache_hours),
+            generate_queries: setting.web_search.generate_queries,
+            research_max_steps: setting
+                .web_search
+                .research_max_steps
+                .trim()
+                .parse()
+                .unwrap_or(all.model.web_search.research_max_steps),
+            research_max_pages: setting
+                .web_search
+                .research_max_pages
+                .trim()
+                .parse()
+                .unwrap_or(all.model.web_search.research_max_pages),
+        };
+
+        all.model.tool_result = config::data::ToolResult {
+            max_chars: setting
+                .tool_result
+                .max_chars
+                .trim()
+                .parse()
+                .unwrap_or(all.model.tool_result.max_chars),
+            summarize: setting.tool_result.summarize,
+        };
+
+        ui.global::<Store>()
+            .set_search_webpages_available(all.model.is_web_search_available());
+
         _ = config::save(all);
+
+        #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+        super::cache::init_search_cache();
     });
 }
 
@@ -144,9 +200,9 @@ fn init_setting(ui: &AppWindow) {
     ui.global::<Store>().set_setting_preference(setting);
 
     ui.global::<Store>()
-        .set_current_model_name(model.chat.model_name.into());
+        .set_current_model_name(model.chat.model_name.as_str().into());
     ui.global::<Store>()
         .set_reasoner_model_available(!model.chat.reasoner_model_name.trim().is_empty());
     ui.global::<Store>()
-        .set_search_webpages_available(!model.google_search.api_key.trim().is_empty());
+        .set_search_webpages_available(model.is_web_search_available());
 }
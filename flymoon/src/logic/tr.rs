@@ -47,6 +47,7 @@ pub fn tr(text: &str) -> String {
         ("chat", "聊天"),
         ("Chat", "聊天"),
         ("Google Search", "谷歌搜索"),
+        ("Web Search", "网络搜索"),
         ("Chat failed", "聊天失败"),
         ("Choose language", "选择语言"),
         ("Choose Theme", "选择主题"),
@@ -203,10 +204,34 @@ pub fn tr(text: &str) -> String {
         ("You can ask me any questions", "你可以问我任何问题哦"),
         ("Info", "提醒"),
         ("Reboot to take setting effect", "重启使配置生效"),
+        ("Cache", "缓存"),
+        ("Remove the cached web pages and images?", "删除缓存的网页和图片?"),
+        ("Remove cache failed", "删除缓存失败"),
+        ("Remove cache successfully", "删除缓存成功"),
+        ("Cache hours (0 means no cache)", "缓存小时数 (0 表示不缓存)"),
+        ("Generate search queries with the chat model", "使用聊天模型生成搜索关键词"),
+        (
+            "The question and the recent conversation are rewritten into 1 to 3 standalone search queries",
+            "将问题和最近的对话改写为 1 到 3 个独立的搜索关键词",
+        ),
+        ("Searched", "已搜索"),
+        ("Research", "研究"),
+        ("Fetch links", "获取链接"),
+        ("Fetch links failed", "获取链接失败"),
+        ("Research notes", "研究笔记"),
+        ("Deep research failed", "深度研究失败"),
+        ("Deep research steps and pages", "深度研究的步数和网页数"),
+        ("Planning the research...", "正在规划研究..."),
+        ("Step", "步骤"),
+        ("Searching", "搜索中"),
+        ("Reading pages", "阅读网页"),
+        ("Reflecting", "反思中"),
+        ("steps", "步数"),
+        ("pages", "网页数"),
         ("Frameless window", "无边框窗口"),
         (
-            "Please go to the 'Settings' page and configure the 'Google Search' related settings",
-            "请到「设置」页面并设置「谷歌搜索」相关配置",
+            "Please go to the 'Settings' page and configure the 'Web Search' related settings",
+            "请到「设置」页面并设置「网络搜索」相关配置",
         ),
         ("Clear", "清空"),
         (
@@ -240,10 +265,102 @@ pub fn tr(text: &str) -> String {
         ("mcp prompt", "mcp提示词"),
         ("Tool", "工具"),
         ("output", "输出"),
+        ("error", "错误"),
         ("No MCP server tools", "没有MCP工具"),
         ("Get MCP server prompt failed", "获取MCP工具提示词失败"),
         ("MCP server tool call failed", "调用MCP服务工具失败"),
         ("MCP server tool not found", "没有找到MCP服务工具"),
+        ("Tool permission", "工具权限"),
+        ("Always allow", "总是允许"),
+        ("Ask every time", "每次询问"),
+        ("Deny", "拒绝"),
+        ("Arguments", "参数"),
+        ("Allow MCP tool call?", "是否允许调用MCP工具？"),
+        ("Remember my decision", "记住我的选择"),
+        ("The user denied this tool call", "用户拒绝了此次工具调用"),
+        (
+            "Reset remembered tool permissions",
+            "重置已记住的工具权限",
+        ),
+        ("Server logs", "服务日志"),
+        ("Uptime", "运行时长"),
+        ("Last ping", "上次心跳"),
+        ("Tools", "工具数"),
+        ("Restarts", "重启次数"),
+        ("ago", "前"),
+        ("Built-in tools", "内置工具"),
+        (
+            "Allowed directories of the file tools, separated by ';'",
+            "文件工具可访问的目录，用';'分隔",
+        ),
+        (
+            "Read a text file in the allowed directories",
+            "读取允许目录中的文本文件",
+        ),
+        (
+            "List the entries of a directory in the allowed directories",
+            "列出允许目录中的目录内容",
+        ),
+        (
+            "Fetch a web page and convert it to plain text",
+            "获取网页并转换为纯文本",
+        ),
+        ("Evaluate a math expression", "计算数学表达式"),
+        ("Get the current local and UTC time", "获取当前本地时间和UTC时间"),
+        ("Read the text in the clipboard", "读取剪贴板中的文本"),
+        ("Audit", "审计"),
+        ("Server, tool or arguments", "服务器、工具或参数"),
+        ("All", "全部"),
+        ("Duration", "耗时"),
+        ("Result size", "结果大小"),
+        ("Session", "会话"),
+        ("Export", "导出"),
+        ("Load audit log failed", "加载审计日志失败"),
+        ("Export failed", "导出失败"),
+        ("Export successfully", "导出成功"),
+        ("Export to", "导出到"),
+        ("Tool list", "工具列表"),
+        ("Load the tools of the running server", "加载运行中服务器的工具"),
+        (
+            "All the tools are enabled. Start the server and load its tools to select them",
+            "已启用所有工具。启动服务器并加载工具后可进行选择",
+        ),
+        (
+            "Only the checked tools are enabled. The tools which the server adds later are disabled until they are loaded and checked",
+            "仅启用勾选的工具。服务器之后新增的工具在加载并勾选前不会启用",
+        ),
+        ("Tool prompt template", "工具提示词模板"),
+        (
+            "{{tools}} is replaced with the tools, empty to use the default template",
+            "{{tools}} 会被替换为工具列表，留空则使用默认模板",
+        ),
+        ("Load tools failed", "加载工具失败"),
+        ("Please start the MCP server first", "请先启动MCP服务器"),
+        ("Tool result", "工具结果"),
+        (
+            "Max characters resent to the model (0 means no limit)",
+            "重新发送给模型的最大字符数（0表示不限制）",
+        ),
+        ("Summarize oversized results with the chat model", "使用聊天模型总结过大的结果"),
+        (
+            "The oversized result is truncated to its head and tail. The full result is still shown in the chat",
+            "过大的结果会被截断为开头和结尾部分，聊天中仍显示完整结果",
+        ),
+        ("Resent to the model", "重新发送给模型的内容"),
+        ("Preset", "预设"),
+        ("Next", "下一步"),
+        ("Create from preset failed", "从预设创建失败"),
+        ("Search provider", "搜索服务"),
+        ("SearXNG URL", "SearXNG地址"),
+        (
+            "The JSON format should be enabled in the settings of the SearXNG instance",
+            "需要在SearXNG实例的设置中启用JSON格式",
+        ),
+        ("No API key is required", "不需要API密钥"),
+        (
+            "Separate multiple values with ';'. The user presets are loaded from the mcp-presets directory beside the config file",
+            "多个值用';'分隔。用户预设从配置文件旁的mcp-presets目录加载",
+        ),
         (
             "Copy latest bot responsing contents",
             "复制最新的机器人回复内容",
@@ -42,6 +42,14 @@ pub fn init(ui: &AppWindow) {
     });
 
     ui.global::<Util>().on_close_window(move || {
+        // kill the mcp server processes before exiting
+        #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+        tokio::spawn(async {
+            mcp::shutdown_all().await;
+            std::process::exit(0);
+        });
+
+        #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
         std::process::exit(0);
     });
 
@@ -0,0 +1,388 @@
+use crate::{
+    config, db,
+    db::def::{ChatEntry, ChatSession, PromptEntry, CHAT_SESSION_TABLE, PROMPT_TABLE},
+    version::VERSION,
+};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use dummy_markdown::{citation, MdUrl};
+use mcp::tool::{Tool, ToolSet};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+const SEARCH_DEFAULT_LIMIT: u64 = 10;
+const SNIPPET_CHARS: usize = 80;
+
+const INSTRUCTIONS: &str = "Query the chat sessions and prompts saved by flymoon. \
+Use `search_chat_sessions` to find a session, then `get_chat_session` to read it.";
+
+pub async fn serve() -> Result<()> {
+    let mut tool_set = ToolSet::default();
+    tool_set.add_tool(SearchChatSessionsTool);
+    tool_set.add_tool(GetChatSessionTool);
+    tool_set.add_tool(ListPromptsTool);
+    tool_set.add_tool(RenderPromptTool);
+    tool_set.add_tool(AppendNoteTool);
+
+    log::info!("start mcp server with db: {}", config::db_path().display());
+
+    mcp::server::serve_stdio(
+        &config::app_name(),
+        VERSION,
+        Some(INSTRUCTIONS.to_string()),
+        tool_set,
+    )
+    .await
+}
+
+fn string_arg(args: &Value, key: &str) -> Result<String> {
+    args.get(key)
+        .and_then(|item| item.as_str())
+        .map(|item| item.to_string())
+        .with_context(|| format!("missing string argument `{key}`"))
+}
+
+async fn all_chat_sessions() -> Result<Vec<ChatSession>> {
+    Ok(db::entry::select_all(CHAT_SESSION_TABLE)
+        .await?
+        .into_iter()
+        .filter_map(|item| serde_json::from_str::<ChatSession>(&item.data).ok())
+        .collect())
+}
+
+async fn all_prompts() -> Result<Vec<PromptEntry>> {
+    Ok(db::entry::select_all(PROMPT_TABLE)
+        .await?
+        .into_iter()
+        .filter_map(|item| serde_json::from_str::<PromptEntry>(&item.data).ok())
+        .collect())
+}
+
+fn session_summary(session: &ChatSession) -> String {
+    session
+        .histories
+        .first()
+        .map(|entry| entry.user.replace(['\r', '\n'], " "))
+        .unwrap_or_default()
+        .chars()
+        .take(SNIPPET_CHARS)
+        .collect()
+}
+
+fn entry_texts(entry: &ChatEntry) -> impl Iterator<Item = &str> {
+    [entry.user.as_str(), entry.bot.as_str()]
+        .into_iter()
+        .chain(entry.mcp.iter().map(|item| item.resp.as_str()))
+}
+
+// The text around the first match of `query`, `query` should be lowercase
+fn snippet(text: &str, query: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    let pos = lower.find(query)?;
+
+    // `to_lowercase` may change the byte length, so count the chars of the lowercase text
+    let start = lower[..pos]
+        .chars()
+        .count()
+        .saturating_sub(SNIPPET_CHARS / 2);
+    Some(
+        text.chars()
+            .skip(start)
+            .take(SNIPPET_CHARS)
+            .collect::<String>()
+            .replace(['\r', '\n'], " "),
+    )
+}
+
+fn session_to_markdown(session: &ChatSession) -> String {
+    let mut md = format!(
+        "# {}\n\n- uuid: {}\n- time: {}\n",
+        session_summary(session),
+        session.uuid,
+        session.time
+    );
+
+    if !session.prompt.is_empty() {
+        md.push_str(&format!("\n## Prompt\n\n{}\n", session.prompt));
+    }
+
+    for entry in session.histories.iter() {
+        md.push_str(&format!("\n## User\n\n{}\n", entry.user));
+
+        if !entry.bot.is_empty() {
+            let sources = entry
+                .search_links
+                .iter()
+                .map(|item| MdUrl {
+                    text: item.title.clone(),
+                    url: item.link.clone(),
+                })
+                .collect::<Vec<_>>();
+
+            md.push_str(&format!(
+                "\n## Assistant\n\n{}\n",
+                citation::to_links(&entry.bot, &sources)
+            ));
+        }
+
+        for item in entry.mcp.iter() {
+            md.push_str(&format!(
+                "\n### Tool: {}\n\n{}\n",
+                item.tool_name, item.resp
+            ));
+        }
+
+        if !entry.search_links.is_empty() {
+            md.push_str("\n### Links\n\n");
+            for (index, link) in entry.search_links.iter().enumerate() {
+                md.push_str(&format!("{}. [{}]({})\n", index + 1, link.title, link.link));
+            }
+        }
+    }
+
+    md
+}
+
+struct SearchChatSessionsTool;
+
+#[async_trait]
+impl Tool for SearchChatSessionsTool {
+    fn name(&self) -> String {
+        "search_chat_sessions".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Search the chat sessions by keyword. Return the uuid, time, summary and matched snippets of the sessions".to_string()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "case-insensitive keyword, empty to list the latest sessions" },
+                "limit": { "type": "integer", "description": format!("max sessions to return, default {SEARCH_DEFAULT_LIMIT}") }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let query = string_arg(&args, "query")?.trim().to_lowercase();
+        let limit = args
+            .get("limit")
+            .and_then(|item| item.as_u64())
+            .unwrap_or(SEARCH_DEFAULT_LIMIT) as usize;
+
+        let mut items = vec![];
+        for session in all_chat_sessions().await? {
+            let snippets = if query.is_empty() {
+                vec![]
+            } else {
+                session
+                    .histories
+                    .iter()
+                    .flat_map(entry_texts)
+                    .filter_map(|text| snippet(text, &query))
+                    .collect::<Vec<_>>()
+            };
+
+            if !query.is_empty() && snippets.is_empty() {
+                continue;
+            }
+
+            items.push((snippets.len(), session, snippets));
+        }
+
+        // the most matched first, then the latest
+        items.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| b.1.time.cmp(&a.1.time)));
+
+        let items = items
+            .into_iter()
+            .take(limit)
+            .map(|(matches, session, snippets)| {
+                json!({
+                    "uuid": session.uuid,
+                    "time": session.time,
+                    "summary": session_summary(&session),
+                    "matches": matches,
+                    "snippets": snippets.into_iter().take(3).collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(serde_json::to_string_pretty(&items)?)
+    }
+}
+
+struct GetChatSessionTool;
+
+#[async_trait]
+impl Tool for GetChatSessionTool {
+    fn name(&self) -> String {
+        "get_chat_session".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Get a chat session as Markdown".to_string()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "uuid": { "type": "string", "description": "uuid of the chat session" }
+            },
+            "required": ["uuid"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let uuid = string_arg(&args, "uuid")?;
+        let item = db::entry::select(CHAT_SESSION_TABLE, &uuid)
+            .await
+            .with_context(|| format!("chat session not found: {uuid}"))?;
+
+        let session = serde_json::from_str::<ChatSession>(&item.data)?;
+        Ok(session_to_markdown(&session))
+    }
+}
+
+struct ListPromptsTool;
+
+#[async_trait]
+impl Tool for ListPromptsTool {
+    fn name(&self) -> String {
+        "list_prompts".to_string()
+    }
+
+    fn description(&self) -> String {
+        "List the saved prompts with their names and shortcuts".to_string()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn call(&self, _args: Value) -> Result<String> {
+        let items = all_prompts()
+            .await?
+            .into_iter()
+            .map(|item| {
+                json!({
+                    "name": item.name,
+                    "shortcut": item.shortcut,
+                    "temperature": item.temperature,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(serde_json::to_string_pretty(&items)?)
+    }
+}
+
+struct RenderPromptTool;
+
+#[async_trait]
+impl Tool for RenderPromptTool {
+    fn name(&self) -> String {
+        "render_prompt".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Render a saved prompt by its shortcut or name, the input is appended to the prompt"
+            .to_string()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "prompt": { "type": "string", "description": "shortcut or name of the prompt" },
+                "input": { "type": "string", "description": "optional user input" }
+            },
+            "required": ["prompt"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let prompt = string_arg(&args, "prompt")?;
+        let input = string_arg(&args, "input").unwrap_or_default();
+
+        let Some(entry) = all_prompts()
+            .await?
+            .into_iter()
+            .find(|item| item.shortcut == prompt || item.name == prompt)
+        else {
+            bail!("prompt not found: {prompt}");
+        };
+
+        if input.is_empty() {
+            Ok(entry.detail)
+        } else {
+            Ok(format!("{}\n\n{}", entry.detail, input))
+        }
+    }
+}
+
+struct AppendNoteTool;
+
+#[async_trait]
+impl Tool for AppendNoteTool {
+    fn name(&self) -> String {
+        "append_note".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Append a note to a chat session, a new session is created if the uuid is not given. Return the uuid of the session".to_string()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "note": { "type": "string", "description": "Markdown text of the note" },
+                "uuid": { "type": "string", "description": "optional uuid of the chat session" }
+            },
+            "required": ["note"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let note = string_arg(&args, "note")?;
+        if note.trim().is_empty() {
+            bail!("note is empty");
+        }
+
+        let entry = ChatEntry {
+            user: note,
+            ..Default::default()
+        };
+
+        match string_arg(&args, "uuid") {
+            Ok(uuid) => {
+                let item = db::entry::select(CHAT_SESSION_TABLE, &uuid)
+                    .await
+                    .with_context(|| format!("chat session not found: {uuid}"))?;
+
+                let mut session = serde_json::from_str::<ChatSession>(&item.data)?;
+                session.histories.push(entry);
+
+                let data = serde_json::to_string(&session)?;
+                db::entry::update(CHAT_SESSION_TABLE, &uuid, &data).await?;
+                Ok(uuid)
+            }
+            _ => {
+                let session = ChatSession {
+                    uuid: Uuid::new_v4().to_string(),
+                    time: cutil::time::local_now("%m-%d %H:%M"),
+                    histories: vec![entry],
+                    ..Default::default()
+                };
+
+                let data = serde_json::to_string(&session)?;
+                db::entry::insert(CHAT_SESSION_TABLE, &session.uuid, &data).await?;
+                Ok(session.uuid)
+            }
+        }
+    }
+}
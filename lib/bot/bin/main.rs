@@ -35,6 +35,7 @@ async fn main() {
     let histories = vec![HistoryChat {
         utext: "hi".to_string(),
         btext: "Hello! 👋 How can I assist you today? 😊".to_string(),
+        ..Default::default()
     }];
 
     let (chat, stop_tx) = Chat::new(prompt, question, config, histories);
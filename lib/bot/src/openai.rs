@@ -11,10 +11,96 @@ use tokio_stream::StreamExt;
 pub mod request {
     use serde::{Deserialize, Serialize};
 
+    // A round of the conversation. It's sent as a user message, an assistant message
+    // with the tool calls and a tool message for each result. The tool calls are only
+    // kept when the tools are declared in the request, otherwise the results are sent
+    // back as a plain user message.
     #[derive(Default, Clone, Debug)]
     pub struct HistoryChat {
         pub utext: String,
         pub btext: String,
+        pub tool_calls: Vec<HistoryToolCall>,
+    }
+
+    #[derive(Default, Clone, Debug)]
+    pub struct HistoryToolCall {
+        pub id: String,
+        pub name: String,
+
+        // JSON object
+        pub arguments: String,
+        pub result: String,
+    }
+
+    impl HistoryChat {
+        pub(crate) fn into_messages(self, tools: &[Tool]) -> Vec<Message> {
+            let declared = self
+                .tool_calls
+                .iter()
+                .all(|call| tools.iter().any(|tool| tool.function.name == call.name));
+
+            if !declared {
+                return self.into_text_messages();
+            }
+
+            let tool_calls = self
+                .tool_calls
+                .iter()
+                .map(|item| ToolCall {
+                    id: item.id.clone(),
+                    kind: "function".to_string(),
+                    function: FunctionCall {
+                        name: item.name.clone(),
+                        arguments: item.arguments.clone(),
+                    },
+                })
+                .collect();
+
+            let mut messages = vec![
+                Message::new(Role::User, self.utext),
+                Message {
+                    tool_calls,
+                    ..Message::new(Role::Assistant, self.btext)
+                },
+            ];
+
+            for item in self.tool_calls.into_iter() {
+                messages.push(Message {
+                    tool_call_id: Some(item.id),
+                    ..Message::new(Role::Tool, item.result)
+                });
+            }
+
+            messages
+        }
+
+        fn into_text_messages(self) -> Vec<Message> {
+            let mut messages = vec![
+                Message::new(Role::User, self.utext),
+                Message::new(Role::Assistant, self.btext),
+            ];
+
+            if !self.tool_calls.is_empty() {
+                let results = self
+                    .tool_calls
+                    .into_iter()
+                    .map(|item| {
+                        format!(
+                            "tool: {}\narguments: {}\nresult:\n{}",
+                            item.name, item.arguments, item.result
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+
+                messages.push(Message::new(
+                    Role::User,
+                    format!("The tool results:\n\n{results}"),
+                ));
+            }
+
+            messages
+        }
     }
 
     #[derive(Serialize, Deserialize, Default, Debug, Clone)]
@@ -33,12 +119,97 @@ pub mod request {
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub temperature: Option<f32>,
+
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub tools: Vec<Tool>,
+
+        // The tools are called by the prompt, they're only declared for the history
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub tool_choice: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Role {
+        System,
+        User,
+        Assistant,
+        Tool,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct ToolCall {
+        pub id: String,
+
+        #[serde(rename = "type")]
+        pub kind: String,
+
+        pub function: FunctionCall,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
-    pub(crate) struct Message {
-        pub role: String,
+    pub struct Tool {
+        #[serde(rename = "type")]
+        pub kind: String,
+
+        pub function: FunctionDefinition,
+    }
+
+    impl Tool {
+        pub fn new(
+            name: impl ToString,
+            description: impl ToString,
+            parameters: serde_json::Value,
+        ) -> Self {
+            Self {
+                kind: "function".to_string(),
+                function: FunctionDefinition {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    parameters,
+                },
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct FunctionDefinition {
+        pub name: String,
+        pub description: String,
+
+        // JSON schema
+        pub parameters: serde_json::Value,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct FunctionCall {
+        pub name: String,
+        pub arguments: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct Message {
+        pub role: Role,
         pub content: String,
+
+        // only for the assistant messages
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub tool_calls: Vec<ToolCall>,
+
+        // only for the tool messages
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub tool_call_id: Option<String>,
+    }
+
+    impl Message {
+        pub fn new(role: Role, content: impl ToString) -> Self {
+            Self {
+                role,
+                content: content.to_string(),
+                tool_calls: vec![],
+                tool_call_id: None,
+            }
+        }
     }
 }
 
@@ -83,7 +254,10 @@ pub mod response {
 #[derive(Debug)]
 pub struct Chat {
     pub config: request::APIConfig,
-    messages: Vec<request::Message>,
+    prompt: String,
+    question: String,
+    chats: Vec<request::HistoryChat>,
+    tools: Vec<request::Tool>,
     stop_rx: mpsc::Receiver<()>,
 }
 
@@ -96,32 +270,12 @@ impl Chat {
     ) -> (Chat, mpsc::Sender<()>) {
         let (stop_tx, stop_rx) = mpsc::channel();
 
-        let mut messages = vec![];
-        messages.push(request::Message {
-            role: "system".to_string(),
-            content: prompt.to_string(),
-        });
-
-        for item in chats.into_iter() {
-            messages.push(request::Message {
-                role: "user".to_string(),
-                content: item.utext,
-            });
-
-            messages.push(request::Message {
-                role: "assistant".to_string(),
-                content: item.btext,
-            })
-        }
-
-        messages.push(request::Message {
-            role: "user".to_string(),
-            content: question.to_string(),
-        });
-
         (
             Chat {
-                messages,
+                prompt: prompt.to_string(),
+                question: question.to_string(),
+                chats,
+                tools: vec![],
                 config,
                 stop_rx,
             },
@@ -129,6 +283,29 @@ impl Chat {
         )
     }
 
+    pub fn with_tools(mut self, tools: Vec<request::Tool>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    fn messages(&mut self) -> Vec<request::Message> {
+        let mut messages = vec![request::Message::new(
+            request::Role::System,
+            std::mem::take(&mut self.prompt),
+        )];
+
+        for item in std::mem::take(&mut self.chats).into_iter() {
+            messages.extend(item.into_messages(&self.tools));
+        }
+
+        messages.push(request::Message::new(
+            request::Role::User,
+            std::mem::take(&mut self.question),
+        ));
+
+        messages
+    }
+
     fn headers(&self) -> HeaderMap {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
@@ -142,16 +319,22 @@ impl Chat {
         headers
     }
 
-    pub async fn start(self, id: u64, cb: impl Fn(response::StreamTextItem)) -> Result<()> {
+    pub async fn start(mut self, id: u64, cb: impl Fn(response::StreamTextItem)) -> Result<()> {
         let headers = self.headers();
         let client = reqwest::Client::new();
 
         let url = format!("{}{}", self.config.api_base_url, "/chat/completions");
         let request_body = request::ChatCompletion {
-            messages: self.messages,
+            messages: self.messages(),
             model: self.config.api_model,
             temperature: self.config.temperature,
             stream: true,
+            tool_choice: if self.tools.is_empty() {
+                None
+            } else {
+                Some("none".to_string())
+            },
+            tools: self.tools,
         };
 
         let mut stream = client
@@ -256,3 +439,79 @@ impl Chat {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::request::*;
+    use serde_json::json;
+
+    fn history_chat() -> HistoryChat {
+        HistoryChat {
+            utext: "list /tmp".to_string(),
+            btext: "calling".to_string(),
+            tool_calls: vec![HistoryToolCall {
+                id: "call_1".to_string(),
+                name: "list_directory".to_string(),
+                arguments: r#"{"path":"/tmp"}"#.to_string(),
+                result: "a.txt".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn history_chat_into_messages() {
+        let tools = vec![Tool::new(
+            "list_directory",
+            "list a directory",
+            json!({ "type": "object" }),
+        )];
+
+        let messages = serde_json::to_value(history_chat().into_messages(&tools)).unwrap();
+        assert_eq!(
+            messages,
+            json!([
+                { "role": "user", "content": "list /tmp" },
+                {
+                    "role": "assistant",
+                    "content": "calling",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "list_directory", "arguments": "{\"path\":\"/tmp\"}" }
+                    }]
+                },
+                { "role": "tool", "content": "a.txt", "tool_call_id": "call_1" }
+            ])
+        );
+    }
+
+    #[test]
+    fn history_chat_into_text_messages() {
+        let messages = serde_json::to_value(history_chat().into_messages(&[])).unwrap();
+        assert_eq!(
+            messages,
+            json!([
+                { "role": "user", "content": "list /tmp" },
+                { "role": "assistant", "content": "calling" },
+                {
+                    "role": "user",
+                    "content": "The tool results:\n\ntool: list_directory\narguments: {\"path\":\"/tmp\"}\nresult:\na.txt"
+                }
+            ])
+        );
+
+        let chat = HistoryChat {
+            utext: "hi".to_string(),
+            btext: "hello".to_string(),
+            tool_calls: vec![],
+        };
+        let messages = serde_json::to_value(chat.into_messages(&[])).unwrap();
+        assert_eq!(
+            messages,
+            json!([
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": "hello" }
+            ])
+        );
+    }
+}
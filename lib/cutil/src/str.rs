@@ -9,6 +9,23 @@ pub fn split_string_to_fixed_length_parts(input: &str, length: usize) -> Vec<Str
         .collect()
 }
 
+// Keep the head and tail of the input which has more than `max_chars` chars.
+// `sep` gets the omitted chars count and returns the text placed in the middle.
+pub fn truncate_middle(input: &str, max_chars: usize, sep: impl Fn(usize) -> String) -> String {
+    let count = input.chars().count();
+    if count <= max_chars {
+        return input.to_string();
+    }
+
+    let tail_chars = max_chars / 3;
+    let head_chars = max_chars - tail_chars;
+
+    let head = input.chars().take(head_chars).collect::<String>();
+    let tail = input.chars().skip(count - tail_chars).collect::<String>();
+
+    format!("{head}{}{tail}", sep(count - max_chars))
+}
+
 pub fn pretty_size_string(size: u64) -> String {
     match size {
         s if s < 1024 => format!("{}B", size),
@@ -25,3 +42,18 @@ pub fn random_string(length: usize) -> String {
         .map(|_| chars[rng.random_range(0..chars.len())])
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_middle() {
+        let sep = |n: usize| format!("[{n}]");
+
+        assert_eq!(truncate_middle("hello", 5, sep), "hello");
+        assert_eq!(truncate_middle("hello world", 6, sep), "hell[5]ld");
+        assert_eq!(truncate_middle("你好世界", 3, sep), "你好[1]界");
+        assert_eq!(truncate_middle("hello", 0, sep), "[5]");
+    }
+}
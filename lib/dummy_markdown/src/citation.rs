@@ -0,0 +1,217 @@
+// The `[n]` citation markers in the answer refer to the numbered sources,
+// which start from 1. Both `[1][2]` and `[1, 2]` are supported.
+//
+// The text elements of the parser have no inline links, so the parser emits a
+// link element to the source after the element which cites it. The copied and
+// the exported documents replace the markers with the markdown links instead.
+use super::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static CITATION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[(\d+(?:\s*,\s*\d+)*)\]").unwrap());
+
+fn numbers(marker: &str) -> impl Iterator<Item = usize> + '_ {
+    marker
+        .split(',')
+        .filter_map(|item| item.trim().parse::<usize>().ok())
+}
+
+fn backtick_run(text: &str) -> usize {
+    text.bytes().take_while(|c| *c == b'`').count()
+}
+
+// Split the line into the prose and the inline code spans, e.g. `v[1]`.
+// A span is closed by the same number of backticks, or it is not a span.
+fn split_code_spans(line: &str) -> Vec<(&str, bool)> {
+    let mut parts = vec![];
+    let (mut start, mut pos) = (0, 0);
+
+    while let Some(offset) = line[pos..].find('`') {
+        let open = pos + offset;
+        let ticks = backtick_run(&line[open..]);
+
+        let mut close = None;
+        let mut search = open + ticks;
+        while let Some(offset) = line[search..].find('`') {
+            let run = backtick_run(&line[search + offset..]);
+            if run == ticks {
+                close = Some(search + offset + run);
+                break;
+            }
+            search += offset + run;
+        }
+
+        match close {
+            Some(end) => {
+                parts.push((&line[start..open], false));
+                parts.push((&line[open..end], true));
+                (start, pos) = (end, end);
+            }
+            None => pos = open + ticks,
+        }
+    }
+
+    parts.push((&line[start..], false));
+    parts
+}
+
+// Split the document into the prose and the code, which are the code blocks
+// and the inline code spans. The parts are joined back to the document.
+fn split_code(doc: &str) -> Vec<(&str, bool)> {
+    let mut in_code_block = false;
+    let mut parts = vec![];
+
+    for line in doc.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            parts.push((line, true));
+        } else if in_code_block {
+            parts.push((line, true));
+        } else {
+            parts.extend(split_code_spans(line));
+        }
+    }
+
+    parts
+}
+
+// Return the sources cited in the text, which has no code, in the order of
+// their first appearance. The unknown numbers are ignored.
+pub(crate) fn cited(text: &str, sources: &[MdUrl]) -> Vec<MdUrl> {
+    let mut cited = vec![];
+
+    for cap in CITATION_RE.captures_iter(text) {
+        for n in numbers(&cap[1]) {
+            if n == 0 || n > sources.len() || cited.contains(&n) {
+                continue;
+            }
+            cited.push(n);
+        }
+    }
+
+    cited
+        .into_iter()
+        .map(|n| MdUrl {
+            text: format!("[{n}] {}", sources[n - 1].text),
+            url: sources[n - 1].url.clone(),
+        })
+        .collect()
+}
+
+// Replace the markers with the markdown links to the sources, so the citations
+// are still clickable in the exported document. The code is kept.
+pub fn to_links(doc: &str, sources: &[MdUrl]) -> String {
+    split_code(doc)
+        .into_iter()
+        .map(|(text, is_code)| {
+            if is_code {
+                return text.to_string();
+            }
+
+            CITATION_RE
+                .replace_all(text, |cap: &regex::Captures| {
+                    let links = numbers(&cap[1])
+                        .map(|n| match sources.get(n.wrapping_sub(1)) {
+                            Some(source) => format!("[\\[{n}\\]]({})", source.url),
+                            None => format!("\\[{n}\\]"),
+                        })
+                        .collect::<String>();
+
+                    // keep the markers which are not citations, e.g. `arr[0]`
+                    if links.contains("](") {
+                        links
+                    } else {
+                        cap[0].to_string()
+                    }
+                })
+                .to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources() -> Vec<MdUrl> {
+        vec![
+            MdUrl {
+                text: "Rust".to_string(),
+                url: "https://www.rust-lang.org/".to_string(),
+            },
+            MdUrl {
+                text: "The Book".to_string(),
+                url: "https://doc.rust-lang.org/book/".to_string(),
+            },
+        ]
+    }
+
+    fn links(doc: &str) -> Vec<String> {
+        parser::run_with_sources(doc, false, &sources())
+            .0
+            .into_iter()
+            .filter(|item| item.ty == MdElementType::Link)
+            .map(|item| format!("{} {}", item.link.text, item.link.url))
+            .collect()
+    }
+
+    #[test]
+    fn citations_to_link_elements() {
+        let doc = "Rust is fast [2][1].\n\n- memory safe [1, 2] and `v[1]`\n\n```\nlet a = v[1];\n```\n\nSee [3].";
+        assert_eq!(
+            links(doc),
+            vec![
+                "[2] The Book https://doc.rust-lang.org/book/",
+                "[1] Rust https://www.rust-lang.org/",
+                "[1] Rust https://www.rust-lang.org/",
+                "[2] The Book https://doc.rust-lang.org/book/",
+            ]
+        );
+
+        let elems = parser::run_with_sources("Rust [1] is `v[2]` fast [1].", false, &sources()).0;
+        assert_eq!(
+            elems.iter().map(|item| item.ty).collect::<Vec<_>>(),
+            vec![MdElementType::Text, MdElementType::Link]
+        );
+        assert_eq!(elems[0].text, "Rust [1] is v[2] fast [1].");
+
+        let doc = "# Rust [2]\n\n| a [1] |\n|---|\n| b [2] |\n\n- a [1]\n  - b [2]";
+        let elems = parser::run_with_sources(doc, false, &sources()).0;
+        assert_eq!(
+            elems.iter().map(|item| item.ty).collect::<Vec<_>>(),
+            vec![
+                MdElementType::Heading,
+                MdElementType::Link,
+                MdElementType::Table,
+                MdElementType::Link,
+                MdElementType::Link,
+                MdElementType::ListItem,
+                MdElementType::Link,
+                MdElementType::ListItem,
+                MdElementType::Link,
+            ]
+        );
+        assert_eq!(elems[8].link.text, "[2] The Book");
+
+        assert!(links("`v[1]` and ``a `[2]` b``").is_empty());
+        assert_eq!(parser::run("Rust [1].", false).0.len(), 1);
+    }
+
+    #[test]
+    fn skip_inline_code() {
+        let doc = "Index `v[1]` and ``a `[2]` b`` of [1]. Unclosed `[2].";
+        assert_eq!(
+            to_links(doc, &sources()),
+            "Index `v[1]` and ``a `[2]` b`` of [\\[1\\]](https://www.rust-lang.org/). Unclosed `[\\[2\\]](https://doc.rust-lang.org/book/)."
+        );
+    }
+
+    #[test]
+    fn citations_to_links() {
+        let doc = "Rust is fast [1, 2] and `v[0]`.\n```\nlet a = v[1];\n```\nSee [3].";
+        assert_eq!(
+            to_links(doc, &sources()),
+            "Rust is fast [\\[1\\]](https://www.rust-lang.org/)[\\[2\\]](https://doc.rust-lang.org/book/) and `v[0]`.\n```\nlet a = v[1];\n```\nSee [3]."
+        );
+    }
+}
@@ -1,3 +1,4 @@
+pub mod citation;
 pub mod parser;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
@@ -10,6 +11,9 @@ pub enum MdElementType {
     Heading,
     CodeBlock,
 
+    // a `[n]` citation marker which refers to the numbered sources
+    Link,
+
     Table,
     TableHead,
     TableRow,
@@ -55,6 +59,7 @@ pub struct MdElement {
     pub code_block: MdCodeBlock,
     pub list_item: MdListItem,
     pub heading: MdHeading,
+    pub link: MdUrl,
 
     pub table: MdTable,
     pub table_cell: String,
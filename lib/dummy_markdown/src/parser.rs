@@ -5,6 +5,7 @@ use regex::Regex;
 #[derive(Debug, Clone)]
 enum MarkdownElement {
     Text(String),
+    Code(String),
     Math(String),
     Url(String),
     List(Vec<MarkdownElement>),
@@ -33,9 +34,21 @@ enum MarkdownElement {
 struct GenerateMdElemUserData {
     list_level: i32,
     link_urls: Vec<MdUrl>,
+
+    // the sources of the `[n]` citation markers
+    sources: Vec<MdUrl>,
 }
 
 pub fn run(doc: &str, parser_math: bool) -> (Vec<MdElement>, Vec<MdUrl>) {
+    run_with_sources(doc, parser_math, &[])
+}
+
+// The citation markers which refer to the sources are followed by the link elements
+pub fn run_with_sources(
+    doc: &str,
+    parser_math: bool,
+    sources: &[MdUrl],
+) -> (Vec<MdElement>, Vec<MdUrl>) {
     let mut items = vec![];
     let mut link_urls = vec![];
 
@@ -55,7 +68,10 @@ pub fn run(doc: &str, parser_math: bool) -> (Vec<MdElement>, Vec<MdUrl>) {
                     let mut elems_iter = elems.iter_mut();
                     let elems_iter_ref: &mut dyn Iterator<Item = &mut MarkdownElement> =
                         &mut elems_iter;
-                    let mut user_data = GenerateMdElemUserData::default();
+                    let mut user_data = GenerateMdElemUserData {
+                        sources: sources.to_vec(),
+                        ..Default::default()
+                    };
 
                     generate_ui_elements(elems_iter_ref, &mut ui_elems, &mut user_data);
 
@@ -82,7 +98,10 @@ pub fn run(doc: &str, parser_math: bool) -> (Vec<MdElement>, Vec<MdUrl>) {
         let mut ui_elems = vec![];
         let mut elems_iter = elems.iter_mut();
         let elems_iter_ref: &mut dyn Iterator<Item = &mut MarkdownElement> = &mut elems_iter;
-        let mut user_data = GenerateMdElemUserData::default();
+        let mut user_data = GenerateMdElemUserData {
+            sources: sources.to_vec(),
+            ..Default::default()
+        };
 
         generate_ui_elements(elems_iter_ref, &mut ui_elems, &mut user_data);
 
@@ -120,10 +139,15 @@ fn parse_events(parser: &mut Parser<'_>) -> Vec<MarkdownElement> {
                 return elems;
             }
             Event::Text(text) => {
-                items.push(MarkdownElement::Text(text.into_string()));
+                // the brackets may split the text, e.g. `[1]`
+                if let Some(MarkdownElement::Text(last)) = items.last_mut() {
+                    last.push_str(&text);
+                } else {
+                    items.push(MarkdownElement::Text(text.into_string()));
+                }
             }
             Event::Code(code) => {
-                items.push(MarkdownElement::Text(code.into_string()));
+                items.push(MarkdownElement::Code(code.into_string()));
             }
             Event::Start(Tag::CodeBlock(kind)) => {
                 let lang = match kind {
@@ -220,6 +244,21 @@ fn generate_ui_elements(
                     text: text.clone().into(),
                     ..Default::default()
                 });
+
+                for link in citation::cited(text, &user_data.sources) {
+                    ui_elems.push(MdElement {
+                        ty: MdElementType::Link,
+                        link,
+                        ..Default::default()
+                    });
+                }
+            }
+            MarkdownElement::Code(code) => {
+                ui_elems.push(MdElement {
+                    ty: MdElementType::Text,
+                    text: code.clone(),
+                    ..Default::default()
+                });
             }
             MarkdownElement::Link(elems) => {
                 if elems.len() != 2 {
@@ -229,7 +268,7 @@ fn generate_ui_elements(
                 let mut ui_url = MdUrl::default();
                 for item in elems.iter() {
                     match item {
-                        MarkdownElement::Text(text) => {
+                        MarkdownElement::Text(text) | MarkdownElement::Code(text) => {
                             ui_url.text = text.clone();
 
                             ui_elems.push(MdElement {
@@ -304,6 +343,7 @@ fn generate_ui_elements(
                     },
                     ..Default::default()
                 });
+                ui_elems.extend(take_links(&mut heading_elems));
             }
             MarkdownElement::Paragraph(elems) => {
                 let mut paragraph_elems = vec![];
@@ -312,6 +352,7 @@ fn generate_ui_elements(
                     &mut elems_iter;
 
                 generate_ui_elements(&mut elems_iter_ref, &mut paragraph_elems, user_data);
+                let links = take_links(&mut paragraph_elems);
 
                 let mut text = String::default();
                 for item in paragraph_elems.into_iter() {
@@ -337,6 +378,8 @@ fn generate_ui_elements(
                         ..Default::default()
                     });
                 }
+
+                ui_elems.extend(links);
             }
             MarkdownElement::List(elems) => {
                 let mut list_elems = vec![];
@@ -360,11 +403,15 @@ fn generate_ui_elements(
 
                 let mut list_item_text = String::default();
                 let mut code_block = MdCodeBlock::default();
+                let mut links = vec![];
 
                 for item in list_item_elems.iter() {
                     if item.ty == MdElementType::Text {
                         list_item_text.push_str(&item.text);
-                    } else if item.ty == MdElementType::ListItem {
+                    } else if item.ty == MdElementType::Link && !list_item_text.is_empty() {
+                        // the links follow the text of the item
+                        push_link(&mut links, item.clone());
+                    } else if item.ty == MdElementType::ListItem || item.ty == MdElementType::Link {
                         if !list_item_text.is_empty() {
                             ui_elems.push(MdElement {
                                 ty: MdElementType::ListItem,
@@ -375,6 +422,7 @@ fn generate_ui_elements(
                                 ..Default::default()
                             });
                             list_item_text.clear();
+                            ui_elems.append(&mut links);
                         }
 
                         ui_elems.push(item.clone());
@@ -394,6 +442,7 @@ fn generate_ui_elements(
                         ..Default::default()
                     });
                 }
+                ui_elems.append(&mut links);
 
                 if !code_block.code.is_empty() {
                     ui_elems.push(MdElement {
@@ -410,6 +459,7 @@ fn generate_ui_elements(
                     &mut elems_iter;
 
                 generate_ui_elements(&mut elems_iter_ref, &mut table_item_elems, user_data);
+                let links = take_links(&mut table_item_elems);
 
                 let (mut head, mut rows) = (vec![], vec![]);
 
@@ -426,6 +476,7 @@ fn generate_ui_elements(
                     table: MdTable { head, rows },
                     ..Default::default()
                 });
+                ui_elems.extend(links);
             }
             MarkdownElement::TableHead(elems) => {
                 let mut table_head_item_elems = vec![];
@@ -434,6 +485,7 @@ fn generate_ui_elements(
                     &mut elems_iter;
 
                 generate_ui_elements(&mut elems_iter_ref, &mut table_head_item_elems, user_data);
+                let links = take_links(&mut table_head_item_elems);
 
                 let table_head = table_head_item_elems
                     .into_iter()
@@ -446,6 +498,7 @@ fn generate_ui_elements(
                     table_head,
                     ..Default::default()
                 });
+                ui_elems.extend(links);
             }
             MarkdownElement::TableRow(elems) => {
                 let mut table_row_item_elems = vec![];
@@ -454,6 +507,7 @@ fn generate_ui_elements(
                     &mut elems_iter;
 
                 generate_ui_elements(&mut elems_iter_ref, &mut table_row_item_elems, user_data);
+                let links = take_links(&mut table_row_item_elems);
 
                 let table_row = table_row_item_elems
                     .into_iter()
@@ -466,6 +520,7 @@ fn generate_ui_elements(
                     table_row,
                     ..Default::default()
                 });
+                ui_elems.extend(links);
             }
             MarkdownElement::TableCell(elems) => {
                 let mut table_cell_item_elems = vec![];
@@ -474,6 +529,7 @@ fn generate_ui_elements(
                     &mut elems_iter;
 
                 generate_ui_elements(&mut elems_iter_ref, &mut table_cell_item_elems, user_data);
+                let links = take_links(&mut table_cell_item_elems);
 
                 let mut text = String::default();
                 for item in table_cell_item_elems.into_iter() {
@@ -487,12 +543,35 @@ fn generate_ui_elements(
                     table_cell: text,
                     ..Default::default()
                 });
+                ui_elems.extend(links);
             }
             _ => (),
         }
     }
 }
 
+fn push_link(links: &mut Vec<MdElement>, item: MdElement) {
+    if !links.iter().any(|link| link.link.text == item.link.text) {
+        links.push(item);
+    }
+}
+
+// Take the link elements out of the block, which are placed after it
+fn take_links(elems: &mut Vec<MdElement>) -> Vec<MdElement> {
+    let mut links = vec![];
+
+    elems.retain(|item| {
+        if item.ty == MdElementType::Link {
+            push_link(&mut links, item.clone());
+            false
+        } else {
+            true
+        }
+    });
+
+    links
+}
+
 fn split_text_and_latex(text: &str) -> Vec<MarkdownElement> {
     let mut last_end = 0;
     let mut items = vec![];
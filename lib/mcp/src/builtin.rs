@@ -0,0 +1,565 @@
+use crate::tool::{Tool, ToolSet};
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use cutil::chrono::{Local, Utc};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+
+// The MCP server tools with this prefix are rejected, so they can't pass for the built-in tools
+pub const NAME_PREFIX: &str = "builtin_";
+
+pub const READ_FILE: &str = "builtin_read_file";
+pub const LIST_DIRECTORY: &str = "builtin_list_directory";
+pub const FETCH_URL: &str = "builtin_fetch_url";
+pub const CALCULATOR: &str = "builtin_calculator";
+pub const CURRENT_TIME: &str = "builtin_current_time";
+pub const CLIPBOARD_READ: &str = "builtin_clipboard_read";
+
+// The tools which send the local data to the model, so the user should be asked by default
+pub const LOCAL_DATA_TOOLS: [&str; 3] = [READ_FILE, LIST_DIRECTORY, CLIPBOARD_READ];
+
+const READ_FILE_MAX_BYTES: u64 = 256 * 1024;
+
+#[derive(Clone, Default)]
+pub struct BuiltinOptions {
+    // the file tools only access files in these directories
+    pub allowed_dirs: Vec<PathBuf>,
+    pub clipboard_reader: Option<fn() -> Result<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuiltinToolInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub fn builtin_tool_infos() -> Vec<BuiltinToolInfo> {
+    vec![
+        BuiltinToolInfo {
+            name: READ_FILE,
+            description: "Read a text file in the allowed directories",
+        },
+        BuiltinToolInfo {
+            name: LIST_DIRECTORY,
+            description: "List the entries of a directory in the allowed directories",
+        },
+        BuiltinToolInfo {
+            name: FETCH_URL,
+            description: "Fetch a web page and convert it to plain text",
+        },
+        BuiltinToolInfo {
+            name: CALCULATOR,
+            description: "Evaluate a math expression",
+        },
+        BuiltinToolInfo {
+            name: CURRENT_TIME,
+            description: "Get the current local and UTC time",
+        },
+        BuiltinToolInfo {
+            name: CLIPBOARD_READ,
+            description: "Read the text in the clipboard",
+        },
+    ]
+}
+
+// Tools with the same name already in `tool_set` are not replaced
+pub fn add_builtin_tools(tool_set: &mut ToolSet, names: &[String], options: &BuiltinOptions) {
+    for name in names {
+        if tool_set.get_tool(name).is_some() {
+            continue;
+        }
+
+        match name.as_str() {
+            READ_FILE => tool_set.add_tool(ReadFileTool::new(options.allowed_dirs.clone())),
+            LIST_DIRECTORY => {
+                tool_set.add_tool(ListDirectoryTool::new(options.allowed_dirs.clone()))
+            }
+            FETCH_URL => tool_set.add_tool(FetchUrlTool),
+            CALCULATOR => tool_set.add_tool(CalculatorTool),
+            CURRENT_TIME => tool_set.add_tool(CurrentTimeTool),
+            CLIPBOARD_READ => {
+                if let Some(reader) = options.clipboard_reader {
+                    tool_set.add_tool(ClipboardReadTool::new(reader));
+                }
+            }
+            _ => log::warn!("unknown builtin tool: {name}"),
+        }
+    }
+}
+
+fn description_of(name: &str) -> String {
+    builtin_tool_infos()
+        .into_iter()
+        .find(|item| item.name == name)
+        .map(|item| item.description.to_string())
+        .unwrap_or_default()
+}
+
+fn string_arg(args: &Value, key: &str) -> Result<String> {
+    args.get(key)
+        .and_then(|item| item.as_str())
+        .map(|item| item.to_string())
+        .with_context(|| format!("missing string argument `{key}`"))
+}
+
+// Resolve `path` and make sure it's inside one of the allowed directories
+fn sandboxed_path(allowed_dirs: &[PathBuf], path: &str) -> Result<PathBuf> {
+    if allowed_dirs.is_empty() {
+        bail!("no allowed directories are configured");
+    }
+
+    let path = Path::new(path)
+        .canonicalize()
+        .with_context(|| format!("invalid path: {path}"))?;
+
+    let allowed = allowed_dirs
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| path.starts_with(dir));
+
+    if allowed {
+        return Ok(path);
+    }
+
+    bail!("{} is not in the allowed directories", path.display())
+}
+
+pub struct ReadFileTool {
+    allowed_dirs: Vec<PathBuf>,
+}
+
+impl ReadFileTool {
+    pub fn new(allowed_dirs: Vec<PathBuf>) -> Self {
+        Self { allowed_dirs }
+    }
+}
+
+#[async_trait]
+impl Tool for ReadFileTool {
+    fn name(&self) -> String {
+        READ_FILE.to_string()
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> String {
+        description_of(READ_FILE)
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "absolute path of the file" }
+            },
+            "required": ["path"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let path = sandboxed_path(&self.allowed_dirs, &string_arg(&args, "path")?)?;
+
+        let metadata = tokio::fs::metadata(&path).await?;
+        if metadata.len() > READ_FILE_MAX_BYTES {
+            bail!(
+                "file is too large: {} bytes, the limit is {} bytes",
+                metadata.len(),
+                READ_FILE_MAX_BYTES
+            );
+        }
+
+        Ok(tokio::fs::read_to_string(&path).await?)
+    }
+}
+
+pub struct ListDirectoryTool {
+    allowed_dirs: Vec<PathBuf>,
+}
+
+impl ListDirectoryTool {
+    pub fn new(allowed_dirs: Vec<PathBuf>) -> Self {
+        Self { allowed_dirs }
+    }
+}
+
+#[async_trait]
+impl Tool for ListDirectoryTool {
+    fn name(&self) -> String {
+        LIST_DIRECTORY.to_string()
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> String {
+        description_of(LIST_DIRECTORY)
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "absolute path of the directory" }
+            },
+            "required": ["path"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let path = sandboxed_path(&self.allowed_dirs, &string_arg(&args, "path")?)?;
+
+        let mut entries = vec![];
+        let mut dir = tokio::fs::read_dir(&path).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let is_dir = entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false);
+            let name = entry.file_name().to_string_lossy().to_string();
+            entries.push(if is_dir { format!("{name}/") } else { name });
+        }
+        entries.sort();
+
+        Ok(entries.join("\n"))
+    }
+}
+
+pub struct FetchUrlTool;
+
+#[async_trait]
+impl Tool for FetchUrlTool {
+    fn name(&self) -> String {
+        FETCH_URL.to_string()
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> String {
+        description_of(FETCH_URL)
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "http or https url" }
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let url = string_arg(&args, "url")?;
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            bail!("only http and https urls are supported");
+        }
+
+        match search::req_link(&url).await? {
+            Some(content) => Ok(content),
+            _ => bail!("no content in {url}"),
+        }
+    }
+}
+
+pub struct CalculatorTool;
+
+#[async_trait]
+impl Tool for CalculatorTool {
+    fn name(&self) -> String {
+        CALCULATOR.to_string()
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "{}. Support + - * / % ^, parentheses, pi, e and functions: sqrt, abs, ln, log, sin, cos, tan",
+            description_of(CALCULATOR)
+        )
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string", "description": "e.g. (1 + 2) * sqrt(16)" }
+            },
+            "required": ["expression"]
+        })
+    }
+
+    async fn call(&self, args: Value) -> Result<String> {
+        let expression = string_arg(&args, "expression")?;
+        Ok(calc::eval(&expression)?.to_string())
+    }
+}
+
+pub struct CurrentTimeTool;
+
+#[async_trait]
+impl Tool for CurrentTimeTool {
+    fn name(&self) -> String {
+        CURRENT_TIME.to_string()
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> String {
+        description_of(CURRENT_TIME)
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn call(&self, _args: Value) -> Result<String> {
+        Ok(json!({
+            "local": Local::now().to_rfc3339(),
+            "utc": Utc::now().to_rfc3339(),
+        })
+        .to_string())
+    }
+}
+
+pub struct ClipboardReadTool {
+    reader: fn() -> Result<String>,
+}
+
+impl ClipboardReadTool {
+    pub fn new(reader: fn() -> Result<String>) -> Self {
+        Self { reader }
+    }
+}
+
+#[async_trait]
+impl Tool for ClipboardReadTool {
+    fn name(&self) -> String {
+        CLIPBOARD_READ.to_string()
+    }
+
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> String {
+        description_of(CLIPBOARD_READ)
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn call(&self, _args: Value) -> Result<String> {
+        (self.reader)()
+    }
+}
+
+mod calc {
+    use anyhow::{Result, bail};
+
+    // The expression is written by the model, so the nesting is limited
+    // to keep the recursive parser from overflowing the stack
+    const MAX_DEPTH: usize = 64;
+
+    pub fn eval(expression: &str) -> Result<f64> {
+        let mut parser = Parser {
+            chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+            pos: 0,
+            depth: 0,
+        };
+
+        let value = parser.expr()?;
+        if parser.pos != parser.chars.len() {
+            bail!("unexpected character at {}", parser.pos);
+        }
+
+        if !value.is_finite() {
+            bail!("result is not a finite number");
+        }
+
+        Ok(value)
+    }
+
+    struct Parser {
+        chars: Vec<char>,
+        pos: usize,
+        depth: usize,
+    }
+
+    impl Parser {
+        fn peek(&self) -> Option<char> {
+            self.chars.get(self.pos).cloned()
+        }
+
+        fn eat(&mut self, c: char) -> bool {
+            if self.peek() == Some(c) {
+                self.pos += 1;
+                true
+            } else {
+                false
+            }
+        }
+
+        // expr := term (('+' | '-') term)*
+        fn expr(&mut self) -> Result<f64> {
+            let mut value = self.term()?;
+            loop {
+                if self.eat('+') {
+                    value += self.term()?;
+                } else if self.eat('-') {
+                    value -= self.term()?;
+                } else {
+                    return Ok(value);
+                }
+            }
+        }
+
+        // term := unary (('*' | '/' | '%') unary)*
+        fn term(&mut self) -> Result<f64> {
+            let mut value = self.unary()?;
+            loop {
+                if self.eat('*') {
+                    value *= self.unary()?;
+                } else if self.eat('/') {
+                    value /= self.unary()?;
+                } else if self.eat('%') {
+                    value %= self.unary()?;
+                } else {
+                    return Ok(value);
+                }
+            }
+        }
+
+        // unary := '-' unary | power
+        // All the nesting goes through it, so the depth is counted here.
+        fn unary(&mut self) -> Result<f64> {
+            if self.depth >= MAX_DEPTH {
+                bail!("expression is nested too deeply");
+            }
+
+            self.depth += 1;
+            let value = if self.eat('-') {
+                self.unary().map(|value| -value)
+            } else {
+                self.power()
+            };
+            self.depth -= 1;
+
+            value
+        }
+
+        // power := atom ('^' unary)?
+        fn power(&mut self) -> Result<f64> {
+            let base = self.atom()?;
+            if self.eat('^') {
+                return Ok(base.powf(self.unary()?));
+            }
+            Ok(base)
+        }
+
+        // atom := number | '(' expr ')' | ident | ident '(' expr ')'
+        fn atom(&mut self) -> Result<f64> {
+            if self.eat('(') {
+                let value = self.expr()?;
+                if !self.eat(')') {
+                    bail!("missing ')'");
+                }
+                return Ok(value);
+            }
+
+            let start = self.pos;
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() || c == '.' => {
+                    while matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '.') {
+                        self.pos += 1;
+                    }
+
+                    let number = self.chars[start..self.pos].iter().collect::<String>();
+                    Ok(number.parse::<f64>()?)
+                }
+                Some(c) if c.is_ascii_alphabetic() => {
+                    while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric()) {
+                        self.pos += 1;
+                    }
+
+                    let ident = self.chars[start..self.pos].iter().collect::<String>();
+                    match ident.as_str() {
+                        "pi" => return Ok(std::f64::consts::PI),
+                        "e" => return Ok(std::f64::consts::E),
+                        _ => (),
+                    }
+
+                    if !self.eat('(') {
+                        bail!("unknown identifier: {ident}");
+                    }
+                    let arg = self.expr()?;
+                    if !self.eat(')') {
+                        bail!("missing ')'");
+                    }
+
+                    Ok(match ident.as_str() {
+                        "sqrt" => arg.sqrt(),
+                        "abs" => arg.abs(),
+                        "ln" => arg.ln(),
+                        "log" => arg.log10(),
+                        "sin" => arg.sin(),
+                        "cos" => arg.cos(),
+                        "tan" => arg.tan(),
+                        _ => bail!("unknown function: {ident}"),
+                    })
+                }
+                _ => bail!("unexpected end of expression"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculator() -> Result<()> {
+        assert_eq!(calc::eval("1 + 2 * 3")?, 7.0);
+        assert_eq!(calc::eval("(1 + 2) * 3")?, 9.0);
+        assert_eq!(calc::eval("-2 ^ 2")?, -4.0);
+        assert_eq!(calc::eval("2 ^ 3 ^ 2")?, 512.0);
+        assert_eq!(calc::eval("2 ^ -1")?, 0.5);
+        assert_eq!(calc::eval("sqrt(16) + abs(-1)")?, 5.0);
+        assert_eq!(calc::eval("10 % 4")?, 2.0);
+        assert!((calc::eval("sin(pi / 2)")? - 1.0).abs() < 1e-9);
+
+        assert!(calc::eval("1 +").is_err());
+        assert!(calc::eval("1 / 0").is_err());
+        assert!(calc::eval("foo(1)").is_err());
+        assert!(CalculatorTool.is_builtin());
+        Ok(())
+    }
+
+    #[test]
+    fn calculator_depth() -> Result<()> {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(calc::eval(&nested(30))?, 1.0);
+        assert!(calc::eval(&nested(100_000)).is_err());
+        assert!(calc::eval(&format!("{}1", "-".repeat(100_000))).is_err());
+        assert!(calc::eval(&format!("sqrt({})", nested(100_000))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sandbox() -> Result<()> {
+        let dir = std::env::temp_dir();
+        let allowed_dirs = vec![dir.clone()];
+
+        assert!(sandboxed_path(&allowed_dirs, &dir.to_string_lossy()).is_ok());
+        assert!(sandboxed_path(&allowed_dirs, "/").is_err());
+        assert!(sandboxed_path(&[], &dir.to_string_lossy()).is_err());
+        Ok(())
+    }
+}
@@ -1,7 +1,83 @@
-use anyhow::Result;
+use crate::{handler::McpClientHandler, supervisor, tool::CallOptions};
+use anyhow::{Result, bail};
+use once_cell::sync::Lazy;
 use rmcp::{service::RunningService, RoleClient, ServiceExt};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, process::Stdio};
+use std::{collections::HashMap, process::Stdio, sync::Mutex};
+use tokio::process::{Child, Command};
+
+// `${secret:NAME}` is resolved by the secret resolver, other `${NAME}` by the host environment
+const SECRET_VAR_PREFIX: &str = "secret:";
+
+const SANDBOX_PROGRAM: &str = "bwrap";
+
+// Mounted read-only if they exist, so that the common interpreters can run in the sandbox
+const SANDBOX_SYSTEM_PATHS: &[&str] = &[
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/etc",
+    "/opt",
+    "/nix",
+    "/run/systemd/resolve",
+];
+
+pub type SecretResolver = fn(&str) -> Option<String>;
+
+static SECRET_RESOLVER: Lazy<Mutex<Option<SecretResolver>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn set_secret_resolver(resolver: SecretResolver) {
+    *SECRET_RESOLVER.lock().unwrap() = Some(resolver);
+}
+
+fn resolve_var(name: &str) -> Option<String> {
+    match name.strip_prefix(SECRET_VAR_PREFIX) {
+        Some(name) => {
+            let resolver = *SECRET_RESOLVER.lock().unwrap();
+            resolver.and_then(|resolver| resolver(name))
+        }
+        _ => std::env::var(name).ok(),
+    }
+}
+
+// Replace `${NAME}` with the resolved value, an undefined variable is an error.
+// The error message contains the variable name only, never the value.
+fn interpolate(text: &str, resolve: &dyn Fn(&str) -> Option<String>) -> Result<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+
+        let Some(end) = rest[start..].find('}') else {
+            bail!("unclosed `${{` in `{text}`");
+        };
+
+        let name = &rest[start + 2..start + end];
+        match resolve(name) {
+            Some(value) => output.push_str(&value),
+            _ => bail!("undefined variable `{name}`"),
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn interpolate_all(
+    items: &[String],
+    resolve: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<String>> {
+    items
+        .iter()
+        .map(|item| interpolate(item, resolve))
+        .collect()
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct RawMcpServerConfig {
@@ -28,6 +104,90 @@ struct RawServerConfig {
 
     #[serde(default)]
     env: HashMap<String, String>,
+
+    #[serde(default)]
+    cwd: Option<String>,
+
+    #[serde(default, rename = "clearEnv")]
+    clear_env: bool,
+
+    #[serde(default)]
+    sandbox: Option<SandboxConfig>,
+
+    #[serde(default, rename = "autoRestart")]
+    auto_restart: bool,
+
+    // seconds
+    #[serde(default, rename = "idleTimeout")]
+    idle_timeout: Option<u64>,
+
+    #[serde(default, flatten)]
+    options: CallOptions,
+}
+
+// Run the stdio server with bubblewrap on Linux. Only the system paths,
+// `paths`, `readOnlyPaths` and `cwd` are visible to the server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SandboxConfig {
+    // writable paths
+    #[serde(default)]
+    paths: Vec<String>,
+
+    #[serde(default, rename = "readOnlyPaths")]
+    read_only_paths: Vec<String>,
+
+    #[serde(default = "sandbox_default_network")]
+    network: bool,
+}
+
+fn sandbox_default_network() -> bool {
+    true
+}
+
+impl SandboxConfig {
+    fn interpolate(&self, resolve: &dyn Fn(&str) -> Option<String>) -> Result<Self> {
+        Ok(Self {
+            paths: interpolate_all(&self.paths, resolve)?,
+            read_only_paths: interpolate_all(&self.read_only_paths, resolve)?,
+            network: self.network,
+        })
+    }
+
+    fn bwrap_args(&self, command: &str, args: &[String], cwd: Option<&str>) -> Vec<String> {
+        let mut items = ["--die-with-parent", "--unshare-all"]
+            .into_iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<_>>();
+
+        if self.network {
+            items.push("--share-net".to_string());
+        }
+
+        for path in SANDBOX_SYSTEM_PATHS {
+            items.extend(["--ro-bind-try", path, path].map(|item| item.to_string()));
+        }
+
+        items.extend(
+            ["--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"].map(|item| item.to_string()),
+        );
+
+        for path in self.read_only_paths.iter() {
+            items.extend(["--ro-bind".to_string(), path.clone(), path.clone()]);
+        }
+
+        for path in self.paths.iter() {
+            items.extend(["--bind".to_string(), path.clone(), path.clone()]);
+        }
+
+        if let Some(cwd) = cwd {
+            items.extend(["--bind", cwd, cwd, "--chdir", cwd].map(|item| item.to_string()));
+        }
+
+        items.push("--".to_string());
+        items.push(command.to_string());
+        items.extend(args.iter().cloned());
+        items
+    }
 }
 
 impl RawMcpServerConfig {
@@ -42,6 +202,13 @@ pub(crate) struct McpServerConfig {
     pub(crate) name: String,
     #[serde(flatten)]
     transport: McpServerTransportConfig,
+    #[serde(default, rename = "autoRestart")]
+    pub(crate) auto_restart: bool,
+    // Stop the unused client after the seconds. It's started again when it's used
+    #[serde(default, rename = "idleTimeout")]
+    pub(crate) idle_timeout: Option<u64>,
+    #[serde(default, flatten)]
+    pub(crate) options: CallOptions,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -56,6 +223,12 @@ enum McpServerTransportConfig {
         args: Vec<String>,
         #[serde(default)]
         env: HashMap<String, String>,
+        #[serde(default)]
+        cwd: Option<String>,
+        #[serde(default, rename = "clearEnv")]
+        clear_env: bool,
+        #[serde(default)]
+        sandbox: Option<SandboxConfig>,
     },
 }
 
@@ -73,41 +246,117 @@ impl McpServerConfig {
         let mut transport: McpServerTransportConfig = McpServerTransportConfig::Sse {
             url: Default::default(),
         };
+        let mut options = CallOptions::default();
+        let mut auto_restart = false;
+        let mut idle_timeout = None;
 
         if raw_config.mcp_servers.servers.contains_key("url") {
             for (k, v) in raw_config.mcp_servers.servers.into_iter() {
                 name = k;
+                options = v.options;
+                auto_restart = v.auto_restart;
+                idle_timeout = v.idle_timeout;
                 transport = McpServerTransportConfig::Sse { url: v.url };
             }
         } else {
             for (k, v) in raw_config.mcp_servers.servers.into_iter() {
                 name = k;
+                options = v.options;
+                auto_restart = v.auto_restart;
+                idle_timeout = v.idle_timeout;
                 transport = McpServerTransportConfig::Stdio {
                     command: v.command,
                     args: v.args,
                     env: v.env,
+                    cwd: v.cwd,
+                    clear_env: v.clear_env,
+                    sandbox: v.sandbox,
                 };
             }
         }
-        Ok(McpServerConfig { name, transport })
+        Ok(McpServerConfig {
+            name,
+            transport,
+            auto_restart,
+            idle_timeout,
+            options,
+        })
     }
 
-    pub(crate) async fn start(&self) -> Result<RunningService<RoleClient, ()>> {
+    // Return the child process of the stdio server to be supervised
+    pub(crate) async fn start(
+        &self,
+    ) -> Result<(RunningService<RoleClient, McpClientHandler>, Option<Child>)> {
+        let handler = McpClientHandler::new(self.name.clone());
+
         let client = match &self.transport {
             McpServerTransportConfig::Sse { url } => {
+                let url = interpolate(url, &resolve_var)?;
                 let transport =
                     rmcp::transport::sse_client::SseClientTransport::start(url.as_str()).await?;
-                ().serve(transport).await?
+                (handler.serve(transport).await?, None)
             }
-            McpServerTransportConfig::Stdio { command, args, env } => {
-                let mut cmd = tokio::process::Command::new(command);
-                cmd.args(args)
-                    .envs(env)
-                    .stderr(Stdio::inherit())
-                    .stdout(Stdio::inherit());
-
-                let transport = rmcp::transport::child_process::TokioChildProcess::new(cmd)?;
-                ().serve(transport).await?
+            McpServerTransportConfig::Stdio {
+                command,
+                args,
+                env,
+                cwd,
+                clear_env,
+                sandbox,
+            } => {
+                let command = interpolate(command, &resolve_var)?;
+                let args = interpolate_all(args, &resolve_var)?;
+                let cwd = cwd
+                    .as_ref()
+                    .map(|item| interpolate(item, &resolve_var))
+                    .transpose()?;
+                let env = env
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), interpolate(v, &resolve_var)?)))
+                    .collect::<Result<HashMap<_, _>>>()?;
+
+                let mut cmd = match sandbox {
+                    Some(sandbox) => {
+                        if !cfg!(target_os = "linux") {
+                            bail!("sandbox of {} is only supported on Linux", self.name);
+                        }
+
+                        let sandbox = sandbox.interpolate(&resolve_var)?;
+                        let mut cmd = Command::new(SANDBOX_PROGRAM);
+                        cmd.args(sandbox.bwrap_args(&command, &args, cwd.as_deref()));
+                        cmd
+                    }
+                    _ => {
+                        let mut cmd = Command::new(&command);
+                        cmd.args(&args);
+                        cmd
+                    }
+                };
+
+                if *clear_env {
+                    cmd.env_clear();
+                }
+
+                if let Some(cwd) = &cwd {
+                    cmd.current_dir(cwd);
+                }
+
+                cmd.envs(env)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true);
+
+                let mut child = cmd.spawn()?;
+                let (Some(stdout), Some(stdin)) = (child.stdout.take(), child.stdin.take()) else {
+                    bail!("take stdio of {} failed", self.name);
+                };
+
+                if let Some(stderr) = child.stderr.take() {
+                    supervisor::capture_stderr(self.name.clone(), stderr);
+                }
+
+                (handler.serve((stdout, stdin)).await?, Some(child))
             }
         };
         Ok(client)
@@ -223,6 +472,120 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn mcp_config_call_options_from_raw_str() -> Result<()> {
+        let stdio_json_data = r#"
+        {
+            "mcpServers": {
+                "fetch": {
+                    "command": "uvx",
+                    "args": ["mcp-server-fetch"],
+                    "timeout": 10,
+                    "toolTimeouts": {
+                        "fetch": 120
+                    },
+                    "parallel": true
+                }
+            }
+        }
+        "#;
+
+        let parsed: McpServerConfig = McpServerConfig::from_raw_str(stdio_json_data)?;
+        assert!(parsed.options.parallel);
+        assert_eq!(parsed.idle_timeout, None);
+        assert_eq!(parsed.options.tool_timeout("fetch").as_secs(), 120);
+        assert_eq!(parsed.options.tool_timeout("other").as_secs(), 10);
+
+        let parsed =
+            McpServerConfig::from_raw_str(r#"{"mcpServers": {"foo": {"command": "foo"}}}"#)?;
+        assert!(!parsed.options.parallel);
+        assert_eq!(
+            parsed.options.tool_timeout("foo").as_secs(),
+            crate::tool::DEFAULT_TOOL_CALL_TIMEOUT_SECS
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mcp_config_process_options_from_raw_str() -> Result<()> {
+        let stdio_json_data = r#"
+        {
+            "mcpServers": {
+                "filesystem": {
+                    "command": "npx",
+                    "args": ["-y", "@modelcontextprotocol/server-filesystem", "${HOME}/docs"],
+                    "cwd": "/tmp",
+                    "clearEnv": true,
+                    "idleTimeout": 600,
+                    "sandbox": {
+                        "paths": ["${HOME}/docs"]
+                    }
+                }
+            }
+        }
+        "#;
+
+        let parsed = McpServerConfig::from_raw_str(stdio_json_data)?;
+        assert_eq!(parsed.idle_timeout, Some(600));
+
+        let McpServerTransportConfig::Stdio {
+            cwd,
+            clear_env,
+            sandbox,
+            ..
+        } = parsed.transport
+        else {
+            bail!("not a stdio config");
+        };
+
+        assert_eq!(cwd.as_deref(), Some("/tmp"));
+        assert!(clear_env);
+
+        let sandbox = sandbox.unwrap();
+        assert_eq!(sandbox.paths, vec!["${HOME}/docs".to_string()]);
+        assert!(sandbox.network);
+        Ok(())
+    }
+
+    #[test]
+    fn interpolate_vars() -> Result<()> {
+        let resolve = |name: &str| match name {
+            "HOME" => Some("/home/foo".to_string()),
+            "secret:TOKEN" => Some("abc".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(interpolate("no vars", &resolve)?, "no vars");
+        assert_eq!(
+            interpolate("${HOME}/docs:${secret:TOKEN}", &resolve)?,
+            "/home/foo/docs:abc"
+        );
+
+        let e = interpolate("${UNKNOWN}", &resolve).unwrap_err();
+        assert_eq!(e.to_string(), "undefined variable `UNKNOWN`");
+        assert!(interpolate("${HOME", &resolve).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sandbox_bwrap_args() {
+        let sandbox = SandboxConfig {
+            paths: vec!["/data".to_string()],
+            read_only_paths: vec!["/models".to_string()],
+            network: false,
+        };
+
+        let args = sandbox.bwrap_args("node", &["server.js".to_string()], Some("/work"));
+        let args = args.join(" ");
+
+        assert!(args.starts_with("--die-with-parent --unshare-all --ro-bind-try /usr /usr"));
+        assert!(!args.contains("--share-net"));
+        assert!(args.contains("--ro-bind /models /models"));
+        assert!(args.contains("--bind /data /data"));
+        assert!(args.ends_with("--bind /work /work --chdir /work -- node server.js"));
+    }
+
     #[test]
     fn mcp_config() -> Result<()> {
         let stdio_json_data = r#"
@@ -0,0 +1,32 @@
+use rmcp::{ClientHandler, RoleClient, service::NotificationContext};
+
+// Keep the cached tools, prompts and resources of the server up to date
+pub(crate) struct McpClientHandler {
+    name: String,
+}
+
+impl McpClientHandler {
+    pub(crate) fn new(name: String) -> Self {
+        Self { name }
+    }
+}
+
+impl ClientHandler for McpClientHandler {
+    async fn on_tool_list_changed(&self, context: NotificationContext<RoleClient>) {
+        log::info!("mcp server {} tool list changed", self.name);
+
+        if let Err(e) = crate::refresh_tools(&self.name, context.peer).await {
+            log::warn!("refresh tools of {} failed: {e:?}", self.name);
+        }
+    }
+
+    async fn on_prompt_list_changed(&self, context: NotificationContext<RoleClient>) {
+        log::info!("mcp server {} prompt list changed", self.name);
+        crate::refresh_prompts(&self.name, context.peer).await;
+    }
+
+    async fn on_resource_list_changed(&self, context: NotificationContext<RoleClient>) {
+        log::info!("mcp server {} resource list changed", self.name);
+        crate::refresh_resources(&self.name, context.peer).await;
+    }
+}
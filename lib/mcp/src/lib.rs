@@ -1,93 +1,347 @@
 use anyhow::Result;
 use config::McpServerConfig;
+use handler::McpClientHandler;
 use once_cell::sync::Lazy;
 use rmcp::{
     RoleClient,
+    model::{Prompt, Resource},
     service::{RunningService, ServerSink},
 };
-use std::{collections::HashMap, sync::Mutex};
-use tool::ToolSet;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, Once},
+    time::{Duration, Instant},
+};
+use tokio::task::{JoinHandle, JoinSet};
+use tool::{CallOptions, ToolSet};
 
+pub mod builtin;
 mod config;
+mod handler;
+pub mod preset;
+pub mod schema;
+pub mod server;
+mod supervisor;
 pub mod tool;
 
+pub use config::{SecretResolver, set_secret_resolver};
+pub use supervisor::ServerHealth;
+
+const IDLE_CHECK_INTERVAL_SECS: u64 = 10;
+const SHUTDOWN_TIMEOUT_SECS: u64 = 5;
+
 struct MCPClient {
-    client: RunningService<RoleClient, ()>,
+    client: RunningService<RoleClient, McpClientHandler>,
     tool_set: ToolSet,
+    prompts: Vec<Prompt>,
+    resources: Vec<Resource>,
+    watcher: JoinHandle<()>,
+    idle_timeout: Option<Duration>,
+    last_used: Instant,
+    in_flight: usize,
+}
+
+impl MCPClient {
+    fn acquire(&mut self) {
+        self.in_flight += 1;
+        self.last_used = Instant::now();
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        self.in_flight == 0
+            && self
+                .idle_timeout
+                .is_some_and(|timeout| now.duration_since(self.last_used) >= timeout)
+    }
 }
 
 pub struct Client {
     pub name: String,
     pub client: ServerSink,
     pub tool_set: ToolSet,
+    pub prompts: Vec<Prompt>,
+    pub resources: Vec<Resource>,
+    pub options: CallOptions,
+
+    // Keep the server from the idle timeout until the client is dropped
+    pub usage: UsageGuard,
 }
 
 static MCP_CLIENTS: Lazy<Mutex<HashMap<String, MCPClient>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+static IDLE_WATCHER: Once = Once::new();
+
+// Mark the client as busy until the guard is dropped,
+// so that a long tool call is not stopped by the idle timeout.
+pub struct UsageGuard {
+    name: String,
+}
+
+impl UsageGuard {
+    pub(crate) fn new(name: &str) -> Self {
+        if let Some(client) = MCP_CLIENTS.lock().unwrap().get_mut(name) {
+            client.acquire();
+        }
+
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl Drop for UsageGuard {
+    fn drop(&mut self) {
+        if let Some(client) = MCP_CLIENTS.lock().unwrap().get_mut(&self.name) {
+            client.in_flight = client.in_flight.saturating_sub(1);
+            client.last_used = Instant::now();
+        }
+    }
+}
+
 pub async fn create_mcp_client(config: &str) -> Result<Client> {
     let mcp_config = McpServerConfig::from_raw_str(config)?;
 
     {
-        let clients = MCP_CLIENTS.lock().unwrap();
-        if clients.contains_key(&mcp_config.name) {
-            let mcp_client = clients.get(&mcp_config.name).unwrap();
-            let (client, tool_set) = (
-                mcp_client.client.peer().clone(),
-                mcp_client.tool_set.clone(),
-            );
+        let mut clients = MCP_CLIENTS.lock().unwrap();
+        if let Some(mcp_client) = clients.get_mut(&mcp_config.name) {
+            mcp_client.acquire();
 
             return Ok(Client {
+                usage: UsageGuard {
+                    name: mcp_config.name.clone(),
+                },
                 name: mcp_config.name,
-                client,
-                tool_set,
+                client: mcp_client.client.peer().clone(),
+                tool_set: mcp_client.tool_set.clone(),
+                prompts: mcp_client.prompts.clone(),
+                resources: mcp_client.resources.clone(),
+                options: mcp_config.options,
             });
         }
     }
 
-    let client = mcp_config.start().await?;
+    let (client, child) = mcp_config.start().await?;
     let peer = client.peer().clone();
 
     // init
     _ = peer.peer_info();
 
-    let mut tool_set = ToolSet::default();
-    let tools = tool::get_mcp_tools(peer.clone()).await?;
-    for tool in tools {
-        tool_set.add_tool(tool);
-    }
+    let tool_set = list_tool_set(&mcp_config.name, peer.clone()).await?;
+    let prompts = list_prompts(&mcp_config.name, &peer).await;
+    let resources = list_resources(&mcp_config.name, &peer).await;
+
+    supervisor::on_started(
+        &mcp_config.name,
+        child.as_ref().and_then(|item| item.id()),
+        tool_set.tools().len(),
+    );
 
     {
+        // Hold the lock until inserted, so that an early exit can't be missed by the watcher
         let mut clients = MCP_CLIENTS.lock().unwrap();
+        let watcher = supervisor::watch(
+            mcp_config.name.clone(),
+            config.to_string(),
+            child,
+            peer.clone(),
+            mcp_config.auto_restart,
+        );
+
         clients.insert(
             mcp_config.name.clone(),
             MCPClient {
                 client,
                 tool_set: tool_set.clone(),
+                prompts: prompts.clone(),
+                resources: resources.clone(),
+                watcher,
+                idle_timeout: mcp_config.idle_timeout.map(Duration::from_secs),
+                last_used: Instant::now(),
+                in_flight: 1,
             },
         );
     }
 
+    if mcp_config.idle_timeout.is_some() {
+        start_idle_watcher();
+    }
+
     Ok(Client {
+        usage: UsageGuard {
+            name: mcp_config.name.clone(),
+        },
         name: mcp_config.name,
         client: peer,
         tool_set,
+        prompts,
+        resources,
+        options: mcp_config.options,
     })
 }
 
-pub async fn cancel_mcp_client(name: &str) -> Result<()> {
+async fn list_tool_set(name: &str, peer: ServerSink) -> Result<ToolSet> {
+    let mut tool_set = ToolSet::default();
+    let tools = tool::get_mcp_tools(name, peer).await?;
+    for tool in tools {
+        tool_set.add_tool(tool);
+    }
+
+    Ok(tool_set)
+}
+
+// The prompts and resources are optional, so a server which fails
+// to list them can still be used for its tools
+async fn list_prompts(name: &str, peer: &ServerSink) -> Vec<Prompt> {
+    match peer.peer_info() {
+        Some(info) if info.capabilities.prompts.is_some() => {
+            peer.list_all_prompts().await.unwrap_or_else(|e| {
+                log::warn!("list prompts of {name} failed: {e:?}");
+                vec![]
+            })
+        }
+        _ => vec![],
+    }
+}
+
+async fn list_resources(name: &str, peer: &ServerSink) -> Vec<Resource> {
+    match peer.peer_info() {
+        Some(info) if info.capabilities.resources.is_some() => {
+            peer.list_all_resources().await.unwrap_or_else(|e| {
+                log::warn!("list resources of {name} failed: {e:?}");
+                vec![]
+            })
+        }
+        _ => vec![],
+    }
+}
+
+// Called when the server sends `notifications/tools/list_changed`.
+// The next `create_mcp_client` returns the refreshed `ToolSet`.
+async fn refresh_tools(name: &str, peer: ServerSink) -> Result<()> {
+    let tool_set = list_tool_set(name, peer).await?;
+    let tool_count = tool_set.tools().len();
+
+    if let Some(client) = MCP_CLIENTS.lock().unwrap().get_mut(name) {
+        client.tool_set = tool_set;
+    }
+
+    supervisor::on_tools_changed(name, tool_count);
+    Ok(())
+}
+
+async fn refresh_prompts(name: &str, peer: ServerSink) {
+    let prompts = list_prompts(name, &peer).await;
+
+    if let Some(client) = MCP_CLIENTS.lock().unwrap().get_mut(name) {
+        client.prompts = prompts;
+    }
+}
+
+async fn refresh_resources(name: &str, peer: ServerSink) {
+    let resources = list_resources(name, &peer).await;
+
+    if let Some(client) = MCP_CLIENTS.lock().unwrap().get_mut(name) {
+        client.resources = resources;
+    }
+}
+
+fn remove_client(name: &str) -> Option<MCPClient> {
+    MCP_CLIENTS.lock().unwrap().remove(name)
+}
+
+// Check the idle clients periodically. The stopped client is started
+// again by `create_mcp_client` when it's used next time.
+fn start_idle_watcher() {
+    IDLE_WATCHER.call_once(|| {
+        tokio::spawn(async {
+            let mut interval = tokio::time::interval(Duration::from_secs(IDLE_CHECK_INTERVAL_SECS));
+
+            loop {
+                interval.tick().await;
+
+                let names = {
+                    let now = Instant::now();
+                    MCP_CLIENTS
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .filter(|(_, client)| client.is_idle(now))
+                        .map(|(name, _)| name.clone())
+                        .collect::<Vec<_>>()
+                };
+
+                for name in names {
+                    if let Err(e) = stop_idle_client(&name).await {
+                        log::warn!("stop idle mcp server {name} failed: {e:?}");
+                    }
+                }
+            }
+        });
+    });
+}
+
+async fn stop_idle_client(name: &str) -> Result<()> {
+    // Check again, the client may be used after it's collected
     let client = {
         let mut clients = MCP_CLIENTS.lock().unwrap();
-        clients.remove(name)
+        match clients.get(name) {
+            Some(client) if client.is_idle(Instant::now()) => clients.remove(name),
+            _ => None,
+        }
     };
 
     if let Some(client) = client {
-        client.client.cancel().await?;
+        log::info!("stop idle mcp server {name}");
+        supervisor::on_stopped(name);
+        stop_client(client).await?;
+    }
+
+    Ok(())
+}
+
+async fn stop_client(client: MCPClient) -> Result<()> {
+    // the child process is killed when the watcher is dropped
+    client.watcher.abort();
+    _ = client.watcher.await;
+    client.client.cancel().await?;
+    Ok(())
+}
+
+pub async fn cancel_mcp_client(name: &str) -> Result<()> {
+    let client = remove_client(name);
+    supervisor::on_stopped(name);
+
+    if let Some(client) = client {
+        stop_client(client).await?;
     }
 
     Ok(())
 }
 
+// Stop all the running servers before the app exits,
+// otherwise the child processes may outlive the app.
+pub async fn shutdown_all() {
+    let clients = MCP_CLIENTS.lock().unwrap().drain().collect::<Vec<_>>();
+
+    let mut tasks = JoinSet::new();
+    for (name, client) in clients {
+        supervisor::on_stopped(&name);
+        tasks.spawn(async move {
+            if let Err(e) = stop_client(client).await {
+                log::warn!("stop mcp server {name} failed: {e:?}");
+            }
+        });
+    }
+
+    if tokio::time::timeout(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS), tasks.join_all())
+        .await
+        .is_err()
+    {
+        log::warn!("stop mcp servers timeout");
+    }
+}
+
 pub fn mcp_server_name_from_config(config: &str) -> Result<String> {
     let config = McpServerConfig::from_raw_str(config)?;
     Ok(config.name)
@@ -96,3 +350,150 @@ pub fn mcp_server_name_from_config(config: &str) -> Result<String> {
 pub fn mcp_server_is_running(name: &str) -> bool {
     MCP_CLIENTS.lock().unwrap().contains_key(name)
 }
+
+pub fn mcp_server_health(name: &str) -> Option<ServerHealth> {
+    supervisor::server_health(name)
+}
+
+pub fn mcp_server_stderr(name: &str) -> Vec<String> {
+    supervisor::server_stderr(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::{
+        Error as McpError, RoleServer, ServerHandler, ServiceExt,
+        model::{
+            ListPromptsResult, ListToolsResult, PaginatedRequestParam, ServerCapabilities,
+            ServerInfo, Tool as McpTool,
+        },
+        service::RequestContext,
+    };
+    use std::sync::Arc;
+
+    // A server whose tools can be changed, and which fails to list its prompts
+    #[derive(Clone)]
+    struct ChangingServer {
+        tools: Arc<Mutex<Vec<McpTool>>>,
+    }
+
+    impl ServerHandler for ChangingServer {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder()
+                    .enable_prompts()
+                    .enable_tools()
+                    .enable_tool_list_changed()
+                    .build(),
+                ..Default::default()
+            }
+        }
+
+        async fn list_tools(
+            &self,
+            _request: Option<PaginatedRequestParam>,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<ListToolsResult, McpError> {
+            Ok(ListToolsResult {
+                tools: self.tools.lock().unwrap().clone(),
+                next_cursor: None,
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _request: Option<PaginatedRequestParam>,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<ListPromptsResult, McpError> {
+            Err(McpError::internal_error("prompts are broken", None))
+        }
+    }
+
+    fn test_tool(name: &'static str) -> McpTool {
+        McpTool {
+            name: name.into(),
+            description: None,
+            input_schema: Arc::new(Default::default()),
+            annotations: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_on_list_changed() -> Result<()> {
+        let name = "refresh-test-server";
+        let server = ChangingServer {
+            tools: Arc::new(Mutex::new(vec![
+                test_tool("a"),
+                test_tool(builtin::READ_FILE),
+            ])),
+        };
+
+        let (server_io, client_io) = tokio::io::duplex(4096);
+        let server_task = tokio::spawn(server.clone().serve(server_io));
+        let client = McpClientHandler::new(name.to_string())
+            .serve(client_io)
+            .await?;
+        let server_service = server_task.await??;
+        let peer = client.peer().clone();
+
+        assert!(list_prompts(name, &peer).await.is_empty());
+
+        // the server can't pass its tools for the built-in tools
+        let tool_set = list_tool_set(name, peer.clone()).await?;
+        assert_eq!(tool_set.tools().len(), 1);
+        assert!(tool_set.get_tool(builtin::READ_FILE).is_none());
+        assert!(!tool_set.get_tool("a").unwrap().is_builtin());
+
+        MCP_CLIENTS.lock().unwrap().insert(
+            name.to_string(),
+            MCPClient {
+                client,
+                tool_set,
+                prompts: vec![],
+                resources: vec![],
+                watcher: tokio::spawn(async {}),
+                idle_timeout: None,
+                last_used: Instant::now(),
+                in_flight: 0,
+            },
+        );
+
+        server.tools.lock().unwrap().push(test_tool("b"));
+        server_service.peer().notify_tool_list_changed().await?;
+
+        let tool_count = || {
+            MCP_CLIENTS
+                .lock()
+                .unwrap()
+                .get(name)
+                .map_or(0, |client| client.tool_set.tools().len())
+        };
+
+        for _ in 0..50 {
+            if tool_count() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(tool_count(), 2);
+
+        // a client in use is never idle
+        let is_idle = || MCP_CLIENTS.lock().unwrap()[name].is_idle(Instant::now());
+        MCP_CLIENTS
+            .lock()
+            .unwrap()
+            .get_mut(name)
+            .unwrap()
+            .idle_timeout = Some(Duration::ZERO);
+        let usage = UsageGuard::new(name);
+        assert!(!is_idle());
+        drop(usage);
+        assert!(is_idle());
+
+        if let Some(client) = remove_client(name) {
+            stop_client(client).await?;
+        }
+        Ok(())
+    }
+}
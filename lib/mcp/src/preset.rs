@@ -0,0 +1,292 @@
+// The templates of the common MCP servers. A catalog file looks like
+// `{"version": 1, "presets": [...]}`. The `{{name}}` placeholders in the
+// `config` of a preset are replaced with the parameter values.
+use crate::config::McpServerConfig;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fs, path::Path};
+
+pub const CATALOG_VERSION: u32 = 1;
+const BUILTIN_CATALOG: &str = include_str!("../data/presets.json");
+
+// the separator of the list parameter values
+const LIST_SEP: char = ';';
+
+#[derive(Debug, Clone, Deserialize)]
+struct Catalog {
+    version: u32,
+
+    #[serde(default)]
+    presets: Vec<Preset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetParam {
+    pub name: String,
+    pub label: String,
+
+    #[serde(default)]
+    pub placeholder: String,
+
+    #[serde(default)]
+    pub default: String,
+
+    #[serde(default)]
+    pub required: bool,
+
+    // The value is split by `;`. A whole `{{name}}` array item
+    // is expanded to an item for each value.
+    #[serde(default)]
+    pub list: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub id: String,
+    pub name: String,
+
+    #[serde(default)]
+    pub shortcut: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub params: Vec<PresetParam>,
+
+    // the `mcpServers` configuration with the placeholders
+    pub config: Value,
+}
+
+impl Preset {
+    // Return the validated server configuration. The strings which contain
+    // the placeholders of the empty optional parameters are removed.
+    pub fn render(&self, values: &HashMap<String, String>) -> Result<String> {
+        let mut items = HashMap::new();
+        for param in self.params.iter() {
+            let value = values
+                .get(&param.name)
+                .map(|item| item.trim())
+                .filter(|item| !item.is_empty())
+                .unwrap_or(param.default.trim());
+
+            let value = if param.list {
+                value
+                    .split(LIST_SEP)
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect::<Vec<_>>()
+            } else if value.is_empty() {
+                vec![]
+            } else {
+                vec![value.to_string()]
+            };
+
+            if param.required && value.is_empty() {
+                bail!("`{}` is required", param.label);
+            }
+
+            items.insert(param.name.as_str(), value);
+        }
+
+        let config = render_value(self.config.clone(), &items)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        let text = serde_json::to_string_pretty(&config)?;
+        if let Some(pos) = text.find("{{") {
+            let name = text[pos + 2..].split("}}").next().unwrap_or_default();
+            bail!("unknown parameter `{name}`");
+        }
+
+        McpServerConfig::from_raw_str(&text)
+            .with_context(|| format!("invalid configuration of preset `{}`", self.id))?;
+
+        Ok(text)
+    }
+}
+
+fn render_value(value: Value, items: &HashMap<&str, Vec<String>>) -> Vec<Value> {
+    match value {
+        Value::String(text) => render_str(&text, items)
+            .into_iter()
+            .map(Value::String)
+            .collect(),
+        Value::Array(values) => vec![Value::Array(
+            values
+                .into_iter()
+                .flat_map(|item| render_value(item, items))
+                .collect(),
+        )],
+        Value::Object(map) => vec![Value::Object(
+            map.into_iter()
+                .filter_map(|(key, item)| {
+                    let mut values = render_value(item, items);
+                    match values.len() {
+                        0 => None,
+                        1 => Some((key, values.remove(0))),
+                        _ => Some((key, join_values(values))),
+                    }
+                })
+                .collect::<Map<_, _>>(),
+        )],
+        value => vec![value],
+    }
+}
+
+fn join_values(values: Vec<Value>) -> Value {
+    Value::String(
+        values
+            .iter()
+            .filter_map(|item| item.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+fn render_str(text: &str, items: &HashMap<&str, Vec<String>>) -> Vec<String> {
+    for (name, values) in items.iter() {
+        if text == format!("{{{{{name}}}}}") {
+            return values.clone();
+        }
+    }
+
+    let mut text = text.to_string();
+    for (name, values) in items.iter() {
+        let placeholder = format!("{{{{{name}}}}}");
+        if !text.contains(&placeholder) {
+            continue;
+        }
+
+        if values.is_empty() {
+            return vec![];
+        }
+
+        text = text.replace(&placeholder, &values.join(","));
+    }
+
+    vec![text]
+}
+
+fn parse_catalog(content: &str) -> Result<Vec<Preset>> {
+    let catalog: Catalog = serde_json::from_str(content)?;
+    if catalog.version > CATALOG_VERSION {
+        bail!(
+            "unsupported catalog version {}, the latest is {CATALOG_VERSION}",
+            catalog.version
+        );
+    }
+
+    Ok(catalog.presets)
+}
+
+pub fn builtin_presets() -> Vec<Preset> {
+    parse_catalog(BUILTIN_CATALOG).expect("invalid built-in preset catalog")
+}
+
+// The built-in presets and the `*.json` catalogs in `user_dir`.
+// A user preset overrides the built-in one with the same id.
+pub fn load_presets(user_dir: &Path) -> Vec<Preset> {
+    let mut presets = builtin_presets();
+
+    let Ok(dir) = fs::read_dir(user_dir) else {
+        return presets;
+    };
+
+    let mut paths = dir
+        .filter_map(|item| item.ok().map(|item| item.path()))
+        .filter(|item| item.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        let user_presets = match fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| parse_catalog(&content))
+        {
+            Ok(items) => items,
+            Err(e) => {
+                log::warn!("load preset catalog {} failed: {e:?}", path.display());
+                continue;
+            }
+        };
+
+        for preset in user_presets {
+            match presets.iter_mut().find(|item| item.id == preset.id) {
+                Some(item) => *item = preset,
+                _ => presets.push(preset),
+            }
+        }
+    }
+
+    presets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(id: &str) -> Preset {
+        builtin_presets()
+            .into_iter()
+            .find(|item| item.id == id)
+            .unwrap()
+    }
+
+    fn values(items: &[(&str, &str)]) -> HashMap<String, String> {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn builtin_presets_render() -> Result<()> {
+        for preset in builtin_presets() {
+            let values = preset
+                .params
+                .iter()
+                .map(|item| (item.name.clone(), "/tmp".to_string()))
+                .collect();
+            preset.render(&values)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn preset_render_params() -> Result<()> {
+        let text = preset("filesystem").render(&values(&[("dirs", "/a; /b;")]))?;
+        let config: Value = serde_json::from_str(&text)?;
+        assert_eq!(
+            config["mcpServers"]["filesystem"]["args"],
+            serde_json::json!(["-y", "@modelcontextprotocol/server-filesystem", "/a", "/b"])
+        );
+
+        let e = preset("filesystem").render(&values(&[])).unwrap_err();
+        assert_eq!(e.to_string(), "`Allowed directories` is required");
+
+        let text = preset("fetch").render(&values(&[]))?;
+        let config: Value = serde_json::from_str(&text)?;
+        assert_eq!(
+            config["mcpServers"]["fetch"]["args"],
+            serde_json::json!(["mcp-server-fetch"])
+        );
+
+        let text = preset("memory").render(&values(&[]))?;
+        let config: Value = serde_json::from_str(&text)?;
+        assert_eq!(config["mcpServers"]["memory"]["env"], serde_json::json!({}));
+
+        let text = preset("brave-search").render(&values(&[]))?;
+        assert!(text.contains("${secret:BRAVE_API_KEY}"));
+        Ok(())
+    }
+
+    #[test]
+    fn preset_catalog_version() {
+        assert!(parse_catalog(r#"{"version": 1}"#).is_ok());
+        assert!(parse_catalog(r#"{"version": 2, "presets": []}"#).is_err());
+    }
+}
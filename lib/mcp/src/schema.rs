@@ -0,0 +1,273 @@
+// A small subset of JSON Schema which is enough for the tool input schemas:
+// `type`, `properties`, `required`, `additionalProperties`, `items`, `enum`,
+// `anyOf` and `oneOf`. The unknown keywords are ignored.
+use serde_json::{Map, Value};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid arguments: {}", self.errors.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+// Validate `args` against `schema` and return the coerced arguments.
+// Common mistakes of the models are coerced, e.g. a stringified JSON object,
+// a number or boolean in a string and `null` instead of an empty object.
+pub fn validate(schema: &Value, args: Value) -> Result<Value, ValidationError> {
+    let args = match args {
+        Value::Null => Value::Object(Map::new()),
+        args => args,
+    };
+
+    let mut errors = vec![];
+    let args = check(schema, args, "$", &mut errors);
+
+    if errors.is_empty() {
+        Ok(args)
+    } else {
+        Err(ValidationError { errors })
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn is_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "number" => value.is_number(),
+        "integer" => match value {
+            Value::Number(n) => {
+                n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0)
+            }
+            _ => false,
+        },
+        ty => type_name(value) == ty,
+    }
+}
+
+fn schema_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(|ty| ty.as_str()).collect(),
+        _ => vec![],
+    }
+}
+
+fn coerce(value: Value, ty: &str) -> Value {
+    let Value::String(text) = &value else {
+        return value;
+    };
+
+    let text = text.trim();
+    let coerced = match ty {
+        "object" | "array" => serde_json::from_str::<Value>(text).ok(),
+        "number" | "integer" => text.parse::<serde_json::Number>().ok().map(Value::Number),
+        "boolean" => match text {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match coerced {
+        Some(coerced) if is_type(&coerced, ty) => coerced,
+        _ => value,
+    }
+}
+
+fn check(schema: &Value, value: Value, path: &str, errors: &mut Vec<String>) -> Value {
+    if !schema.is_object() {
+        return value;
+    }
+
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(schemas)) = schema.get(key) {
+            return check_any_of(schemas, value, path, errors);
+        }
+    }
+
+    let types = schema_types(schema);
+    let value = if types.is_empty() || types.iter().any(|ty| is_type(&value, ty)) {
+        value
+    } else {
+        let value = types
+            .iter()
+            .map(|ty| coerce(value.clone(), ty))
+            .find(|coerced| types.iter().any(|ty| is_type(coerced, ty)))
+            .unwrap_or(value);
+
+        if !types.iter().any(|ty| is_type(&value, ty)) {
+            errors.push(format!(
+                "{path}: expected {}, got {}",
+                types.join(" or "),
+                type_name(&value)
+            ));
+            return value;
+        }
+
+        value
+    };
+
+    match schema.get("enum") {
+        Some(Value::Array(items)) if !items.contains(&value) => {
+            errors.push(format!(
+                "{path}: {value} is not one of {}",
+                Value::Array(items.clone())
+            ));
+        }
+        _ => (),
+    }
+
+    match value {
+        Value::Object(map) => Value::Object(check_object(schema, map, path, errors)),
+        Value::Array(items) => match schema.get("items") {
+            Some(item_schema) => Value::Array(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, item)| check(item_schema, item, &format!("{path}[{i}]"), errors))
+                    .collect(),
+            ),
+            _ => Value::Array(items),
+        },
+        value => value,
+    }
+}
+
+fn check_any_of(schemas: &[Value], value: Value, path: &str, errors: &mut Vec<String>) -> Value {
+    let mut first_errors = None;
+
+    for schema in schemas {
+        let mut sub_errors = vec![];
+        let checked = check(schema, value.clone(), path, &mut sub_errors);
+        if sub_errors.is_empty() {
+            return checked;
+        }
+
+        first_errors.get_or_insert(sub_errors);
+    }
+
+    errors.extend(first_errors.unwrap_or_default());
+    value
+}
+
+fn check_object(
+    schema: &Value,
+    mut map: Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) -> Map<String, Value> {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for key in required.iter().filter_map(|key| key.as_str()) {
+            if !map.contains_key(key) {
+                errors.push(format!("{path}: missing required property `{key}`"));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(|item| item.as_object());
+    let additional = schema.get("additionalProperties");
+
+    for (key, value) in map.iter_mut() {
+        let sub_path = format!("{path}.{key}");
+
+        match properties.and_then(|item| item.get(key)) {
+            Some(sub_schema) => {
+                *value = check(sub_schema, value.take(), &sub_path, errors);
+            }
+            _ => match additional {
+                Some(Value::Bool(false)) => {
+                    errors.push(format!("{path}: unknown property `{key}`"));
+                }
+                Some(sub_schema @ Value::Object(_)) => {
+                    *value = check(sub_schema, value.take(), &sub_path, errors);
+                }
+                _ => (),
+            },
+        }
+    }
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "count": { "type": "integer" },
+                "recursive": { "type": "boolean" },
+                "mode": { "type": "string", "enum": ["fast", "slow"] },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "range": {
+                    "type": "object",
+                    "properties": { "start": { "type": "number" } },
+                    "required": ["start"]
+                }
+            },
+            "required": ["path"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn validate_valid_arguments() {
+        let args = json!({ "path": "/tmp", "count": 3, "tags": ["a"] });
+        assert_eq!(validate(&schema(), args.clone()), Ok(args));
+    }
+
+    #[test]
+    fn validate_coerce_arguments() {
+        let args = json!(
+            r#"{"path": "/tmp", "count": "3", "recursive": "true", "range": "{\"start\": 1.5}"}"#
+        );
+
+        assert_eq!(
+            validate(&schema(), args),
+            Ok(json!({ "path": "/tmp", "count": 3, "recursive": true, "range": { "start": 1.5 } }))
+        );
+
+        assert_eq!(
+            validate(&json!({ "type": "object", "properties": {} }), Value::Null),
+            Ok(json!({}))
+        );
+    }
+
+    #[test]
+    fn validate_invalid_arguments() {
+        let args = json!({ "count": "many", "mode": "medium", "tags": [1], "range": {}, "foo": 1 });
+        let errors = validate(&schema(), args).unwrap_err().errors;
+
+        assert!(errors.contains(&"$: missing required property `path`".to_string()));
+        assert!(errors.contains(&"$.count: expected integer, got string".to_string()));
+        assert!(
+            errors.contains(&"$.mode: \"medium\" is not one of [\"fast\",\"slow\"]".to_string())
+        );
+        assert!(errors.contains(&"$.tags[0]: expected string, got integer".to_string()));
+        assert!(errors.contains(&"$.range: missing required property `start`".to_string()));
+        assert!(errors.contains(&"$: unknown property `foo`".to_string()));
+        assert_eq!(errors.len(), 6);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::tool::ToolSet;
+use anyhow::Result;
+use rmcp::{
+    Error as McpError, RoleServer, ServerHandler, ServiceExt,
+    model::{
+        CallToolRequestParam, CallToolResult, Content, Implementation, JsonObject, ListToolsResult,
+        PaginatedRequestParam, ServerCapabilities, ServerInfo, Tool as McpTool,
+    },
+    service::RequestContext,
+    transport::stdio,
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+// Expose the tools of a `ToolSet` to the MCP clients
+struct ToolSetServer {
+    name: String,
+    version: String,
+    instructions: Option<String>,
+    tool_set: ToolSet,
+}
+
+impl ServerHandler for ToolSetServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            server_info: Implementation {
+                name: self.name.clone(),
+                version: self.version.clone(),
+            },
+            instructions: self.instructions.clone(),
+            ..Default::default()
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let mut tools = self
+            .tool_set
+            .tools()
+            .into_iter()
+            .map(|tool| {
+                let input_schema = match tool.parameters() {
+                    Value::Object(schema) => schema,
+                    _ => JsonObject::default(),
+                };
+
+                McpTool {
+                    name: tool.name().into(),
+                    description: Some(tool.description().into()),
+                    input_schema: Arc::new(input_schema),
+                    annotations: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(ListToolsResult {
+            tools,
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(tool) = self.tool_set.get_tool(&request.name) else {
+            return Err(McpError::invalid_params(
+                format!("tool not found: {}", request.name),
+                None,
+            ));
+        };
+
+        let args = Value::Object(request.arguments.unwrap_or_default());
+        let args = match tool.validate(args) {
+            Ok(args) => args,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e.to_string())])),
+        };
+
+        match tool.call(args).await {
+            Ok(text) => Ok(CallToolResult::success(vec![Content::text(text)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!("{e:?}"))])),
+        }
+    }
+}
+
+// Serve the tools over stdin and stdout until the client disconnects.
+// Logs must not be written to stdout in this mode.
+pub async fn serve_stdio(
+    name: &str,
+    version: &str,
+    instructions: Option<String>,
+    tool_set: ToolSet,
+) -> Result<()> {
+    let server = ToolSetServer {
+        name: name.to_string(),
+        version: version.to_string(),
+        instructions,
+        tool_set,
+    };
+
+    let service = server.serve(stdio()).await?;
+    service.waiting().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtin::{self, BuiltinOptions};
+    use rmcp::model::CallToolRequestParam;
+
+    #[tokio::test]
+    async fn serve_tool_set() -> Result<()> {
+        let mut tool_set = ToolSet::default();
+        builtin::add_builtin_tools(
+            &mut tool_set,
+            &[builtin::CALCULATOR.to_string()],
+            &BuiltinOptions::default(),
+        );
+
+        let server = ToolSetServer {
+            name: "test".to_string(),
+            version: "0.1.0".to_string(),
+            instructions: None,
+            tool_set,
+        };
+
+        let (server_io, client_io) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let service = server.serve(server_io).await?;
+            service.waiting().await?;
+            anyhow::Ok(())
+        });
+
+        let client = ().serve(client_io).await?;
+        let tools = client.list_all_tools().await?;
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, builtin::CALCULATOR);
+
+        let result = client
+            .call_tool(CallToolRequestParam {
+                name: builtin::CALCULATOR.into(),
+                arguments: serde_json::json!({ "expression": "1 + 2" })
+                    .as_object()
+                    .cloned(),
+            })
+            .await?;
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(
+            result.content[0].as_text().map(|item| item.text.as_str()),
+            Some("3")
+        );
+
+        client.cancel().await?;
+        Ok(())
+    }
+}
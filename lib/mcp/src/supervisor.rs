@@ -0,0 +1,276 @@
+use once_cell::sync::Lazy;
+use rmcp::{
+    model::{ClientRequest, PingRequest},
+    service::{PeerRequestOptions, ServerSink},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::{Child, ChildStderr},
+    task::JoinHandle,
+};
+
+const STDERR_MAX_LINES: usize = 500;
+const PING_INTERVAL_SECS: u64 = 30;
+const PING_TIMEOUT_SECS: u64 = 10;
+const RESTART_MAX_ATTEMPTS: u32 = 5;
+const RESTART_MAX_BACKOFF_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Default)]
+pub struct ServerHealth {
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub uptime: Option<Duration>,
+
+    // elapsed time since the last successful ping
+    pub last_ping: Option<Duration>,
+    pub tool_count: usize,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    running: bool,
+    stopped: bool,
+    pid: Option<u32>,
+    started_at: Option<Instant>,
+    last_ping: Option<Instant>,
+    tool_count: usize,
+    restarts: u32,
+    last_error: Option<String>,
+    stderr: VecDeque<String>,
+}
+
+static SERVER_STATES: Lazy<Mutex<HashMap<String, ServerState>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn server_health(name: &str) -> Option<ServerHealth> {
+    let states = SERVER_STATES.lock().unwrap();
+    let state = states.get(name)?;
+
+    Some(ServerHealth {
+        running: state.running,
+        pid: state.pid,
+        uptime: state.started_at.map(|item| item.elapsed()),
+        last_ping: state.last_ping.map(|item| item.elapsed()),
+        tool_count: state.tool_count,
+        restarts: state.restarts,
+        last_error: state.last_error.clone(),
+    })
+}
+
+pub fn server_stderr(name: &str) -> Vec<String> {
+    SERVER_STATES
+        .lock()
+        .unwrap()
+        .get(name)
+        .map(|state| state.stderr.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn capture_stderr(name: String, stderr: ChildStderr) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            log::debug!("[{name}] {line}");
+
+            let mut states = SERVER_STATES.lock().unwrap();
+            let state = states.entry(name.clone()).or_default();
+            if state.stderr.len() >= STDERR_MAX_LINES {
+                state.stderr.pop_front();
+            }
+            state.stderr.push_back(line);
+        }
+    });
+}
+
+pub(crate) fn on_started(name: &str, pid: Option<u32>, tool_count: usize) {
+    let mut states = SERVER_STATES.lock().unwrap();
+    let state = states.entry(name.to_string()).or_default();
+
+    state.running = true;
+    state.stopped = false;
+    state.pid = pid;
+    state.started_at = Some(Instant::now());
+    state.last_ping = None;
+    state.tool_count = tool_count;
+    state.last_error = None;
+}
+
+pub(crate) fn on_tools_changed(name: &str, tool_count: usize) {
+    if let Some(state) = SERVER_STATES.lock().unwrap().get_mut(name) {
+        state.tool_count = tool_count;
+    }
+}
+
+// The user stopped the server, so it should not be restarted
+pub(crate) fn on_stopped(name: &str) {
+    if let Some(state) = SERVER_STATES.lock().unwrap().get_mut(name) {
+        state.running = false;
+        state.stopped = true;
+        state.pid = None;
+        state.started_at = None;
+    }
+}
+
+fn on_exited(name: &str, reason: String) {
+    log::warn!("mcp server {name} exited: {reason}");
+
+    if let Some(state) = SERVER_STATES.lock().unwrap().get_mut(name) {
+        state.running = false;
+        state.pid = None;
+        state.started_at = None;
+        state.last_error = Some(reason);
+    }
+}
+
+fn on_ping(name: &str, result: Result<(), String>) {
+    if let Some(state) = SERVER_STATES.lock().unwrap().get_mut(name) {
+        match result {
+            Ok(_) => {
+                state.last_ping = Some(Instant::now());
+                state.last_error = None;
+            }
+            Err(e) => state.last_error = Some(format!("ping failed: {e}")),
+        }
+    }
+}
+
+// Wait for the server process to exit and ping the server periodically.
+// Aborting the returned handle kills the child process.
+pub(crate) fn watch(
+    name: String,
+    config: String,
+    child: Option<Child>,
+    peer: ServerSink,
+    auto_restart: bool,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut child = child;
+        let mut interval = tokio::time::interval(Duration::from_secs(PING_INTERVAL_SECS));
+
+        let reason = loop {
+            tokio::select! {
+                status = wait_child(&mut child) => {
+                    break match status {
+                        Ok(status) => format!("process exited with {status}"),
+                        Err(e) => format!("wait process failed: {e}"),
+                    };
+                }
+                _ = interval.tick() => {
+                    on_ping(&name, ping(&peer).await);
+
+                    if peer.is_transport_closed() {
+                        break "transport closed".to_string();
+                    }
+                }
+            }
+        };
+
+        on_exited(&name, reason);
+
+        if let Some(client) = crate::remove_client(&name) {
+            _ = client.client.cancel().await;
+        }
+
+        if auto_restart {
+            tokio::spawn(restart(name, config));
+        }
+    })
+}
+
+async fn wait_child(child: &mut Option<Child>) -> std::io::Result<std::process::ExitStatus> {
+    match child {
+        Some(child) => child.wait().await,
+        _ => std::future::pending().await,
+    }
+}
+
+async fn ping(peer: &ServerSink) -> Result<(), String> {
+    let handle = peer
+        .send_request_with_option(
+            ClientRequest::PingRequest(PingRequest {
+                method: Default::default(),
+                extensions: Default::default(),
+            }),
+            PeerRequestOptions {
+                timeout: Some(Duration::from_secs(PING_TIMEOUT_SECS)),
+                meta: None,
+            },
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    handle.await_response().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn restart(name: String, config: String) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        for attempt in 0..RESTART_MAX_ATTEMPTS {
+            let backoff = 2_u64.pow(attempt).min(RESTART_MAX_BACKOFF_SECS);
+            tokio::time::sleep(Duration::from_secs(backoff)).await;
+
+            {
+                let mut states = SERVER_STATES.lock().unwrap();
+                match states.get_mut(&name) {
+                    Some(state) if !state.stopped && !state.running => state.restarts += 1,
+                    _ => return,
+                }
+            }
+
+            match crate::create_mcp_client(&config).await {
+                Ok(_) => {
+                    log::info!("restart mcp server {name} successfully");
+                    return;
+                }
+                Err(e) => {
+                    log::warn!("restart mcp server {name} failed (attempt {attempt}): {e:?}");
+                    on_exited(&name, format!("restart failed: {e}"));
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_health_lifecycle() {
+        let name = "supervisor-test-server";
+        assert!(server_health(name).is_none());
+
+        on_started(name, Some(42), 3);
+        let health = server_health(name).unwrap();
+        assert!(health.running);
+        assert_eq!(health.pid, Some(42));
+        assert_eq!(health.tool_count, 3);
+        assert!(health.uptime.is_some());
+        assert!(health.last_ping.is_none());
+
+        on_ping(name, Ok(()));
+        assert!(server_health(name).unwrap().last_ping.is_some());
+
+        on_exited(name, "process exited with exit status: 1".to_string());
+        let health = server_health(name).unwrap();
+        assert!(!health.running);
+        assert!(health.uptime.is_none());
+        assert_eq!(
+            health.last_error.as_deref(),
+            Some("process exited with exit status: 1")
+        );
+
+        on_stopped(name);
+        assert!(SERVER_STATES.lock().unwrap().get(name).unwrap().stopped);
+    }
+}
@@ -1,12 +1,23 @@
-use anyhow::Result;
+use crate::{
+    UsageGuard, builtin,
+    schema::{self, ValidationError},
+};
+use anyhow::{Result, bail};
 use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
 use rmcp::{
-    model::{CallToolRequestParam, Tool as McpTool},
-    service::ServerSink,
+    model::{
+        CallToolRequest, CallToolRequestParam, CallToolResult, CancelledNotification,
+        CancelledNotificationParam, ClientRequest, RawContent, RequestId, ResourceContents,
+        ServerResult, Tool as McpTool,
+    },
+    service::{PeerRequestOptions, ServerSink},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+pub const DEFAULT_TOOL_CALL_TIMEOUT_SECS: u64 = 60;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -14,22 +25,231 @@ pub struct ToolCall {
     pub arguments: serde_json::Value,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolContent {
+    Text(String),
+    Image {
+        data: Vec<u8>,
+        mime_type: String,
+    },
+    Resource {
+        uri: String,
+        mime_type: Option<String>,
+        text: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolOutput {
+    pub content: Vec<ToolContent>,
+    pub is_error: bool,
+}
+
+impl ToolOutput {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![ToolContent::Text(text.into())],
+            is_error: false,
+        }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![ToolContent::Text(text.into())],
+            is_error: true,
+        }
+    }
+
+    // Bytes of the text, image and resource parts
+    pub fn size(&self) -> usize {
+        self.content
+            .iter()
+            .map(|item| match item {
+                ToolContent::Text(text) => text.len(),
+                ToolContent::Image { data, .. } => data.len(),
+                ToolContent::Resource { text, .. } => text.as_ref().map_or(0, |text| text.len()),
+            })
+            .sum()
+    }
+
+    // Only the text parts are sent back to the model
+    pub fn to_text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|item| match item {
+                ToolContent::Text(text) => Some(text.clone()),
+                ToolContent::Resource {
+                    text: Some(text), ..
+                } => Some(text.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl From<CallToolResult> for ToolOutput {
+    fn from(result: CallToolResult) -> Self {
+        let content = result
+            .content
+            .into_iter()
+            .filter_map(|item| match item.raw {
+                RawContent::Text(text) => Some(ToolContent::Text(text.text)),
+                RawContent::Image(image) => decode_image(&image.data, image.mime_type),
+                RawContent::Resource(resource) => match resource.resource {
+                    ResourceContents::TextResourceContents {
+                        uri,
+                        mime_type,
+                        text,
+                    } => Some(ToolContent::Resource {
+                        uri,
+                        mime_type,
+                        text: Some(text),
+                    }),
+                    ResourceContents::BlobResourceContents {
+                        mime_type: Some(mime_type),
+                        blob,
+                        ..
+                    } if mime_type.starts_with("image/") => decode_image(&blob, mime_type),
+                    ResourceContents::BlobResourceContents { uri, mime_type, .. } => {
+                        Some(ToolContent::Resource {
+                            uri,
+                            mime_type,
+                            text: None,
+                        })
+                    }
+                },
+                RawContent::Audio(_) => None,
+            })
+            .collect();
+
+        Self {
+            content,
+            is_error: result.is_error.unwrap_or(false),
+        }
+    }
+}
+
+fn decode_image(data: &str, mime_type: String) -> Option<ToolContent> {
+    match BASE64.decode(data) {
+        Ok(data) => Some(ToolContent::Image { data, mime_type }),
+        Err(e) => {
+            log::warn!("decode {mime_type} image failed: {e:?}");
+            None
+        }
+    }
+}
+
 #[async_trait]
 pub trait Tool: Send + Sync {
     fn name(&self) -> String;
     fn description(&self) -> String;
     fn parameters(&self) -> Value;
     async fn call(&self, args: Value) -> Result<String>;
+
+    // Only the in-process tools of `builtin` return true, never the MCP server tools
+    fn is_builtin(&self) -> bool {
+        false
+    }
+
+    // Check the arguments against `parameters` and return the coerced arguments
+    fn validate(&self, args: Value) -> Result<Value, ValidationError> {
+        schema::validate(&self.parameters(), args)
+    }
+
+    async fn call_with_timeout(
+        &self,
+        args: Value,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
+        let output = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, self.call(args)).await {
+                Ok(result) => result?,
+                Err(_) => bail!("tool call timed out after {}s", timeout.as_secs()),
+            },
+            _ => self.call(args).await?,
+        };
+
+        Ok(ToolOutput::text(output))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CallOptions {
+    // seconds, fallback to `DEFAULT_TOOL_CALL_TIMEOUT_SECS`
+    #[serde(default)]
+    pub timeout: Option<u64>,
+
+    #[serde(default, rename = "toolTimeouts")]
+    pub tool_timeouts: HashMap<String, u64>,
+
+    // run independent tool calls of a response concurrently
+    #[serde(default)]
+    pub parallel: bool,
+}
+
+impl CallOptions {
+    pub fn tool_timeout(&self, name: &str) -> Duration {
+        let secs = self
+            .tool_timeouts
+            .get(name)
+            .cloned()
+            .or(self.timeout)
+            .unwrap_or(DEFAULT_TOOL_CALL_TIMEOUT_SECS);
+
+        Duration::from_secs(secs)
+    }
+}
+
+// Notify the server to cancel the request if the call future is dropped before the response arrives
+struct CancelOnDrop {
+    server: ServerSink,
+    id: Option<RequestId>,
+}
+
+impl CancelOnDrop {
+    fn disarm(mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+
+        let server = self.server.clone();
+        tokio::spawn(async move {
+            let notification = CancelledNotification {
+                params: CancelledNotificationParam {
+                    request_id: id,
+                    reason: Some("cancelled by user".to_string()),
+                },
+                method: Default::default(),
+                extensions: Default::default(),
+            };
+
+            if let Err(e) = server.send_notification(notification.into()).await {
+                log::warn!("send cancelled notification failed: {e:?}");
+            }
+        });
+    }
 }
 
 pub struct McpToolAdapter {
     tool: McpTool,
     server: ServerSink,
+    server_name: String,
 }
 
 impl McpToolAdapter {
-    pub fn new(tool: McpTool, server: ServerSink) -> Self {
-        Self { tool, server }
+    pub fn new(tool: McpTool, server: ServerSink, server_name: String) -> Self {
+        Self {
+            tool,
+            server,
+            server_name,
+        }
     }
 }
 
@@ -52,23 +272,57 @@ impl Tool for McpToolAdapter {
     }
 
     async fn call(&self, args: Value) -> Result<String> {
+        let output = self.call_with_timeout(args, None).await?;
+        if output.is_error {
+            bail!("{}", output.to_text());
+        }
+
+        Ok(output.to_text())
+    }
+
+    async fn call_with_timeout(
+        &self,
+        args: Value,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
         let arguments = match args {
             Value::Object(map) => Some(map),
             _ => None,
         };
 
-        let call_result = self
+        let _usage = UsageGuard::new(&self.server_name);
+        let handle = self
             .server
-            .call_tool(CallToolRequestParam {
-                name: self.tool.name.clone(),
-                arguments,
-            })
+            .send_cancellable_request(
+                ClientRequest::CallToolRequest(CallToolRequest {
+                    method: Default::default(),
+                    params: CallToolRequestParam {
+                        name: self.tool.name.clone(),
+                        arguments,
+                    },
+                    extensions: Default::default(),
+                }),
+                PeerRequestOptions {
+                    timeout,
+                    meta: None,
+                },
+            )
             .await?;
-        let result = serde_json::to_string(&call_result).unwrap();
 
-        Ok(result)
+        let guard = CancelOnDrop {
+            server: self.server.clone(),
+            id: Some(handle.id.clone()),
+        };
+        let response = handle.await_response().await;
+        guard.disarm();
+
+        match response? {
+            ServerResult::CallToolResult(call_result) => Ok(call_result.into()),
+            _ => bail!("unexpected response of tool call"),
+        }
     }
 }
+
 #[derive(Default, Clone)]
 pub struct ToolSet {
     tools: HashMap<String, Arc<dyn Tool>>,
@@ -86,12 +340,62 @@ impl ToolSet {
     pub fn tools(&self) -> Vec<Arc<dyn Tool>> {
         self.tools.values().cloned().collect()
     }
+
+    pub fn retain(&mut self, f: impl Fn(&str) -> bool) {
+        self.tools.retain(|name, _| f(name));
+    }
 }
 
-pub async fn get_mcp_tools(server: ServerSink) -> Result<Vec<McpToolAdapter>> {
+pub async fn get_mcp_tools(server_name: &str, server: ServerSink) -> Result<Vec<McpToolAdapter>> {
     let tools = server.list_all_tools().await?;
     Ok(tools
         .into_iter()
-        .map(|tool| McpToolAdapter::new(tool, server.clone()))
+        .filter(|tool| {
+            let reserved = tool.name.starts_with(builtin::NAME_PREFIX);
+            if reserved {
+                log::warn!(
+                    "reject tool {} of mcp server {server_name}: the prefix `{}` is reserved",
+                    tool.name,
+                    builtin::NAME_PREFIX
+                );
+            }
+            !reserved
+        })
+        .map(|tool| McpToolAdapter::new(tool, server.clone(), server_name.to_string()))
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{AnnotateAble, Content, RawEmbeddedResource};
+
+    #[test]
+    fn tool_output_from_call_tool_result() {
+        let result = CallToolResult {
+            content: vec![
+                Content::text("hello"),
+                Content::image(BASE64.encode([1, 2, 3]), "image/png"),
+                RawContent::Resource(RawEmbeddedResource {
+                    resource: ResourceContents::text("world", "file:///a.txt"),
+                })
+                .no_annotation(),
+                Content::image("not base64!", "image/png"),
+            ],
+            is_error: Some(true),
+        };
+
+        let output = ToolOutput::from(result);
+        assert!(output.is_error);
+        assert_eq!(output.content.len(), 3);
+        assert_eq!(
+            output.content[1],
+            ToolContent::Image {
+                data: vec![1, 2, 3],
+                mime_type: "image/png".to_string(),
+            }
+        );
+        assert_eq!(output.to_text(), "hello\nworld");
+        assert_eq!(output.size(), 13);
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use search::google;
+use search::{SearchProvider, google, rank::RankOptions};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -9,9 +9,10 @@ async fn main() -> Result<()> {
         cx: "a0e7eb672d5134b97".to_string(),
         api_key: "Your-API-Key".to_string(),
         num: 3,
+        ..Default::default()
     };
 
-    let (text, links) = google::search(query, config).await?;
+    let (text, links) = config.search(query, &RankOptions::default()).await?;
     println!("{text:?}");
     println!("{links:?}");
 
@@ -0,0 +1,119 @@
+use super::{SearchLink, SearchProvider, endpoint_or};
+use anyhow::Result;
+use async_trait::async_trait;
+use cutil::{http, reqwest};
+use serde::Deserialize;
+
+pub const API_URL: &str = "https://api.bing.microsoft.com/v7.0/search";
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SearchResult {
+    #[serde(default)]
+    web_pages: Option<WebPages>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WebPages {
+    #[serde(default)]
+    value: Vec<SearchResultItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchResultItem {
+    name: String,
+    url: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub api_key: String,
+    pub num: u8,
+
+    // Use `API_URL` if it's empty
+    pub endpoint: String,
+}
+
+#[async_trait]
+impl SearchProvider for Config {
+    fn cache_key(&self) -> String {
+        format!(
+            "bing\n{}\n{}",
+            endpoint_or(&self.endpoint, API_URL),
+            self.num
+        )
+    }
+
+    fn num(&self) -> usize {
+        self.num as usize
+    }
+
+    async fn search_links(&self, query: &str) -> Result<Vec<SearchLink>> {
+        let url = format!(
+            "{}?q={}&count={}&responseFilter=Webpages",
+            endpoint_or(&self.endpoint, API_URL),
+            urlencoding::encode(query),
+            self.num
+        );
+
+        let mut headers = http::headers();
+        headers.insert("Ocp-Apim-Subscription-Key", self.api_key.parse()?);
+
+        let result = reqwest::Client::new()
+            .get(&url)
+            .headers(headers)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SearchResult>()
+            .await?;
+        log::info!("{:#?}", result);
+
+        Ok(result
+            .web_pages
+            .map(|pages| pages.value)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| SearchLink {
+                title: item.name,
+                link: item.url,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    const RESPONSE: &str = r#"{
+        "_type": "SearchResponse",
+        "webPages": {
+            "totalEstimatedMatches": 2,
+            "value": [
+                {"id": "0", "name": "Rust", "url": "https://www.rust-lang.org/"},
+                {"id": "1", "name": "The Book", "url": "https://doc.rust-lang.org/book/"}
+            ]
+        }
+    }"#;
+
+    #[tokio::test]
+    async fn bing_search_links() -> Result<()> {
+        let (endpoint, requests) = test_server::serve("application/json", RESPONSE).await;
+        let config = Config {
+            api_key: "bing-key".to_string(),
+            num: 2,
+            endpoint,
+        };
+
+        let links = config.search_links("learn rust").await?;
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].title, "Rust");
+        assert_eq!(links[1].link, "https://doc.rust-lang.org/book/");
+
+        let request = requests.lock().unwrap()[0].to_lowercase();
+        assert!(request.contains("ocp-apim-subscription-key: bing-key"));
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use super::{SearchLink, SearchProvider, endpoint_or};
+use anyhow::Result;
+use async_trait::async_trait;
+use cutil::{http, reqwest};
+use serde::Deserialize;
+
+pub const API_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+
+#[derive(Deserialize, Debug)]
+struct SearchResult {
+    #[serde(default)]
+    web: Option<WebResult>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WebResult {
+    #[serde(default)]
+    results: Vec<SearchResultItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchResultItem {
+    title: String,
+    url: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub api_key: String,
+    pub num: u8,
+
+    // Use `API_URL` if it's empty
+    pub endpoint: String,
+}
+
+#[async_trait]
+impl SearchProvider for Config {
+    fn cache_key(&self) -> String {
+        format!(
+            "brave\n{}\n{}",
+            endpoint_or(&self.endpoint, API_URL),
+            self.num
+        )
+    }
+
+    fn num(&self) -> usize {
+        self.num as usize
+    }
+
+    async fn search_links(&self, query: &str) -> Result<Vec<SearchLink>> {
+        let url = format!(
+            "{}?q={}&count={}",
+            endpoint_or(&self.endpoint, API_URL),
+            urlencoding::encode(query),
+            self.num
+        );
+
+        let mut headers = http::headers();
+        headers.insert("Accept", "application/json".parse()?);
+        headers.insert("X-Subscription-Token", self.api_key.parse()?);
+
+        let result = reqwest::Client::new()
+            .get(&url)
+            .headers(headers)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SearchResult>()
+            .await?;
+        log::info!("{:#?}", result);
+
+        Ok(result
+            .web
+            .map(|web| web.results)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| SearchLink {
+                title: item.title,
+                link: item.url,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    const RESPONSE: &str = r#"{
+        "type": "search",
+        "web": {
+            "type": "search",
+            "results": [
+                {"title": "Rust", "url": "https://www.rust-lang.org/", "description": "A language"}
+            ]
+        }
+    }"#;
+
+    #[tokio::test]
+    async fn brave_search_links() -> Result<()> {
+        let (endpoint, requests) = test_server::serve("application/json", RESPONSE).await;
+        let config = Config {
+            api_key: "brave-key".to_string(),
+            num: 3,
+            endpoint,
+        };
+
+        let links = config.search_links("learn rust").await?;
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].title, "Rust");
+        assert_eq!(links[0].link, "https://www.rust-lang.org/");
+
+        let request = requests.lock().unwrap()[0].to_lowercase();
+        assert!(request.starts_with("get /?q=learn%20rust&count=3 "));
+        assert!(request.contains("x-subscription-token: brave-key"));
+        Ok(())
+    }
+}
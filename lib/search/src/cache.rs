@@ -0,0 +1,240 @@
+// A TTL cache of the search links and the fetched pages on the disk. The links
+// are keyed by the provider and the query, the pages by the url. An expired
+// page is revalidated with its `ETag` or `Last-Modified` before downloading it again.
+use super::SearchLink;
+use anyhow::Result;
+use cutil::crypto;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const LINKS_DIR: &str = "links";
+const PAGES_DIR: &str = "pages";
+
+// The expired pages are kept for revalidation until this times of the TTL
+const STALE_TTL_FACTOR: u64 = 7;
+
+static CACHE: RwLock<Option<Cache>> = RwLock::new(None);
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LinksEntry {
+    timestamp: u64,
+    links: Vec<SearchLink>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PageEntry {
+    pub timestamp: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+
+    // the extracted content of the page
+    pub content: Option<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    pub fn is_fresh(&self, timestamp: u64) -> bool {
+        now().saturating_sub(timestamp) < self.ttl.as_secs()
+    }
+
+    fn path(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir
+            .join(kind)
+            .join(format!("{}.json", crypto::hash(key)))
+    }
+
+    fn read<T: DeserializeOwned>(path: &Path) -> Option<T> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn write<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_json::to_string(value)?)?;
+        Ok(())
+    }
+
+    // Return the links if they are not expired
+    pub fn links(&self, key: &str) -> Option<Vec<SearchLink>> {
+        Self::read::<LinksEntry>(&self.path(LINKS_DIR, key))
+            .filter(|entry| self.is_fresh(entry.timestamp))
+            .map(|entry| entry.links)
+    }
+
+    pub fn save_links(&self, key: &str, links: &[SearchLink]) {
+        let entry = LinksEntry {
+            timestamp: now(),
+            links: links.to_vec(),
+        };
+
+        if let Err(e) = Self::write(&self.path(LINKS_DIR, key), &entry) {
+            log::warn!("save search links cache failed: {e:?}");
+        }
+    }
+
+    // Return the page even if it's expired, so it can be revalidated
+    pub fn page(&self, url: &str) -> Option<PageEntry> {
+        Self::read(&self.path(PAGES_DIR, url))
+    }
+
+    pub fn save_page(&self, url: &str, mut entry: PageEntry) {
+        entry.timestamp = now();
+
+        if let Err(e) = Self::write(&self.path(PAGES_DIR, url), &entry) {
+            log::warn!("save page cache failed: {e:?}");
+        }
+    }
+
+    // Remove the links which are expired and the pages which are too old to revalidate
+    pub fn purge_expired(&self) {
+        let ttl = self.ttl.as_secs();
+        for (kind, max_age) in [(LINKS_DIR, ttl), (PAGES_DIR, ttl * STALE_TTL_FACTOR)] {
+            let Ok(dir) = fs::read_dir(self.dir.join(kind)) else {
+                continue;
+            };
+
+            for path in dir.filter_map(|item| item.ok().map(|item| item.path())) {
+                let is_expired = fs::metadata(&path)
+                    .and_then(|item| item.modified())
+                    .ok()
+                    .and_then(|item| item.elapsed().ok())
+                    .is_none_or(|item| item.as_secs() >= max_age);
+
+                if is_expired {
+                    _ = fs::remove_file(&path);
+                }
+            }
+        }
+    }
+}
+
+// Enable the cache in `dir`. A zero `ttl` disables it.
+pub fn init(dir: impl Into<PathBuf>, ttl: Duration) {
+    let cache = (!ttl.is_zero()).then(|| Cache::new(dir, ttl));
+    if let Some(cache) = cache.as_ref() {
+        cache.purge_expired();
+    }
+
+    *CACHE.write().unwrap() = cache;
+}
+
+pub fn current() -> Option<Cache> {
+    CACHE.read().unwrap().clone()
+}
+
+pub fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|item| item.ok())
+        .map(|item| match item.file_type() {
+            Ok(ty) if ty.is_dir() => dir_size(&item.path()),
+            _ => item.metadata().map(|item| item.len()).unwrap_or_default(),
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    fn temp_cache(name: &str) -> Cache {
+        let dir = std::env::temp_dir().join(format!("search-cache-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        Cache::new(dir, Duration::from_secs(3600))
+    }
+
+    #[test]
+    fn cache_links() {
+        let cache = temp_cache("links");
+        let links = vec![SearchLink {
+            title: "Rust".to_string(),
+            link: "https://www.rust-lang.org/".to_string(),
+        }];
+
+        assert_eq!(cache.links("google\nrust"), None);
+        cache.save_links("google\nrust", &links);
+        assert_eq!(cache.links("google\nrust"), Some(links.clone()));
+        assert_eq!(cache.links("brave\nrust"), None);
+        assert!(dir_size(&cache.dir) > 0);
+
+        let expired = Cache::new(cache.dir.clone(), Duration::ZERO);
+        assert_eq!(expired.links("google\nrust"), None);
+        expired.purge_expired();
+        assert_eq!(dir_size(&cache.dir), 0);
+
+        _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[tokio::test]
+    async fn cache_pages() -> Result<()> {
+        const PAGE: &str = "<html><body><article><p>Ownership is the most unique feature of Rust, and it enables memory safety.</p></article></body></html>";
+
+        let (base_url, requests) = test_server::serve_with(|request| {
+            if request.to_lowercase().contains("if-none-match: \"v1\"") {
+                test_server::response("304 Not Modified", "ETag: \"v1\"\r\n", "")
+            } else {
+                test_server::response(
+                    "200 OK",
+                    "Content-Type: text/html\r\nETag: \"v1\"\r\n",
+                    PAGE,
+                )
+            }
+        })
+        .await;
+
+        let url = format!("{base_url}/page");
+        let cache = temp_cache("pages");
+
+        let content = crate::fetch_page(&url, Some(&cache)).await?;
+        assert!(content.as_ref().unwrap().contains("Ownership"));
+        assert_eq!(cache.page(&url).unwrap().etag.as_deref(), Some("\"v1\""));
+
+        // the fresh page is not requested again
+        assert_eq!(crate::fetch_page(&url, Some(&cache)).await?, content);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // the expired page is revalidated
+        let expired = Cache::new(cache.dir.clone(), Duration::ZERO);
+        assert_eq!(crate::fetch_page(&url, Some(&expired)).await?, content);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert!(
+            requests.lock().unwrap()[1]
+                .to_lowercase()
+                .contains("if-none-match: \"v1\"")
+        );
+
+        _ = fs::remove_dir_all(&cache.dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+// Convert the downloaded documents to text by their content types. The HTML
+// pages are extracted, the PDF text is extracted, the plain text, Markdown
+// and JSON are passed through, and the binaries are skipped.
+//
+// The PDF parser may panic with a malformed document, and a panic aborts the
+// release build. So the PDF text is extracted in a child process, which is the
+// host program run with `PDF_TEXT_COMMAND`. The host calls `pdf_text_main` for
+// the command and sets itself by `set_pdf_text_program`, or the PDFs are skipped.
+use super::extract;
+use anyhow::{Context, Result, bail};
+use cutil::reqwest::Response;
+use once_cell::sync::{Lazy, OnceCell};
+use regex::Regex;
+use std::io::{Read, Write};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use tokio::io::AsyncWriteExt;
+
+pub const PDF_TEXT_COMMAND: &str = "pdf-text";
+
+const PDF_TEXT_TIMEOUT: Duration = Duration::from_secs(30);
+
+static BLANK_LINES_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n\s*\n\s*\n+").unwrap());
+
+static PDF_TEXT_PROGRAM: OnceCell<PathBuf> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentType {
+    Html,
+    Pdf,
+    Text,
+    Binary,
+}
+
+fn mime_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|item| {
+        let (key, value) = item.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+// Guess the type by the extension of the link if the content type is missing or generic
+fn guess_type(link: &str) -> Option<DocumentType> {
+    let path = link.split(['?', '#']).next().unwrap_or_default();
+    let ext = path.rsplit_once('.')?.1.to_lowercase();
+
+    match ext.as_str() {
+        "pdf" => Some(DocumentType::Pdf),
+        "txt" | "md" | "markdown" | "json" | "csv" => Some(DocumentType::Text),
+        "html" | "htm" | "xhtml" => Some(DocumentType::Html),
+        _ => None,
+    }
+}
+
+pub fn document_type(content_type: Option<&str>, link: &str) -> DocumentType {
+    let mime = content_type.map(mime_type).unwrap_or_default();
+
+    match mime.as_str() {
+        "text/html" | "application/xhtml+xml" => DocumentType::Html,
+        "application/pdf" | "application/x-pdf" => DocumentType::Pdf,
+        "application/json" | "application/ld+json" | "application/xml" | "text/xml" => {
+            DocumentType::Text
+        }
+        item if item.ends_with("+json") || item.starts_with("text/") => DocumentType::Text,
+
+        // the pages without a content type are regarded as HTML as before
+        "" => guess_type(link).unwrap_or(DocumentType::Html),
+        "application/octet-stream" | "binary/octet-stream" => {
+            guess_type(link).unwrap_or(DocumentType::Binary)
+        }
+        _ => DocumentType::Binary,
+    }
+}
+
+// Read the body of the response, which should be at most `max_bytes`
+pub async fn read_body(mut resp: Response, max_bytes: usize) -> Result<Vec<u8>> {
+    if let Some(len) = resp.content_length()
+        && len > max_bytes as u64
+    {
+        bail!("the document has {len} bytes, which exceeds the limit {max_bytes}");
+    }
+
+    let mut body = vec![];
+    while let Some(chunk) = resp.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            bail!("the document exceeds the limit {max_bytes} bytes");
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+// Decode the text with the charset of the content type, UTF-8 by default
+fn decode(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(charset)
+        .and_then(|item| encoding_rs::Encoding::for_label(item.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+
+    encoding.decode(body).0.into_owned()
+}
+
+fn normalize_text(text: &str) -> Option<String> {
+    let text = text
+        .lines()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = BLANK_LINES_RE.replace_all(&text, "\n\n").trim().to_string();
+
+    if text.is_empty() { None } else { Some(text) }
+}
+
+pub fn set_pdf_text_program(program: PathBuf) {
+    _ = PDF_TEXT_PROGRAM.set(program);
+}
+
+fn extract_pdf_text(body: &[u8]) -> Result<String> {
+    Ok(pdf_extract::extract_text_from_mem(body)?)
+}
+
+// Read the PDF document from the stdin and write its text to the stdout
+pub fn pdf_text_main() -> Result<()> {
+    let mut body = vec![];
+    std::io::stdin().read_to_end(&mut body)?;
+
+    let text = extract_pdf_text(&body)?;
+    std::io::stdout().write_all(text.as_bytes())?;
+
+    Ok(())
+}
+
+async fn pdf_text(body: Vec<u8>) -> Result<String> {
+    let Some(program) = PDF_TEXT_PROGRAM.get() else {
+        bail!("no program to extract the PDF text");
+    };
+
+    run_pdf_text(program, body).await
+}
+
+async fn run_pdf_text(program: &Path, body: Vec<u8>) -> Result<String> {
+    let mut child = tokio::process::Command::new(program)
+        .arg(PDF_TEXT_COMMAND)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("no stdin of the PDF text process")?;
+    let write = async move {
+        stdin.write_all(&body).await?;
+        stdin.shutdown().await
+    };
+
+    // write and read at the same time, or the full pipes would block both sides
+    let (written, output) = tokio::time::timeout(PDF_TEXT_TIMEOUT, async {
+        tokio::join!(write, child.wait_with_output())
+    })
+    .await
+    .context("the PDF text extraction timed out")?;
+
+    let output = output?;
+    if !output.status.success() {
+        bail!("can't extract the PDF text: {}", output.status);
+    }
+    written?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub async fn to_text(
+    ty: DocumentType,
+    body: Vec<u8>,
+    content_type: Option<&str>,
+) -> Result<Option<String>> {
+    match ty {
+        DocumentType::Html => Ok(extract::main_content(&decode(&body, content_type))),
+        DocumentType::Text => Ok(normalize_text(&decode(&body, content_type))),
+        DocumentType::Pdf => Ok(normalize_text(&pdf_text(body).await?)),
+        DocumentType::Binary => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    #[test]
+    fn detect_document_type() {
+        let link = "https://example.com/a";
+        assert_eq!(
+            document_type(Some("text/html; charset=utf-8"), link),
+            DocumentType::Html
+        );
+        assert_eq!(
+            document_type(Some("application/pdf"), link),
+            DocumentType::Pdf
+        );
+        assert_eq!(
+            document_type(Some("text/markdown"), link),
+            DocumentType::Text
+        );
+        assert_eq!(
+            document_type(Some("application/vnd.api+json"), link),
+            DocumentType::Text
+        );
+        assert_eq!(document_type(Some("image/png"), link), DocumentType::Binary);
+        assert_eq!(document_type(None, link), DocumentType::Html);
+        assert_eq!(
+            document_type(
+                Some("application/octet-stream"),
+                "https://example.com/paper.PDF?download=1"
+            ),
+            DocumentType::Pdf
+        );
+        assert_eq!(
+            document_type(Some("application/octet-stream"), link),
+            DocumentType::Binary
+        );
+    }
+
+    #[tokio::test]
+    async fn convert_documents() -> Result<()> {
+        let text = to_text(
+            DocumentType::Text,
+            b"# Title\n\n\n\nbody  \n".to_vec(),
+            Some("text/markdown"),
+        )
+        .await?;
+        assert_eq!(text.as_deref(), Some("# Title\n\nbody"));
+
+        let (body, _, _) = encoding_rs::GBK.encode("你好");
+        let text = to_text(
+            DocumentType::Text,
+            body.to_vec(),
+            Some("text/plain; charset=GBK"),
+        )
+        .await?;
+        assert_eq!(text.as_deref(), Some("你好"));
+
+        let body = std::fs::read("fixtures/ownership.pdf")?;
+        let text = extract_pdf_text(&body)?;
+        assert!(text.contains("Ownership in Rust"));
+        assert!(text.contains("without a garbage collector"));
+
+        // the PDFs are skipped without the program
+        assert!(to_text(DocumentType::Pdf, body, None).await.is_err());
+        assert_eq!(
+            to_text(DocumentType::Binary, vec![0, 1, 2], None).await?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_documents() -> Result<()> {
+        let (base_url, _) =
+            test_server::serve("text/plain", "Rust 1.0 was released in 2015.").await;
+        let text = crate::fetch_page(&format!("{base_url}/notes.txt"), None).await?;
+        assert_eq!(text.as_deref(), Some("Rust 1.0 was released in 2015."));
+
+        let (base_url, _) = test_server::serve("image/png", "not an image").await;
+        assert_eq!(
+            crate::fetch_page(&format!("{base_url}/a.png"), None).await?,
+            None
+        );
+
+        let resp = cutil::reqwest::get(&base_url).await?;
+        assert!(read_body(resp, 4).await.is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn pdf_text_process() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = |name: &str, body: &str| -> Result<PathBuf> {
+            let path =
+                std::env::temp_dir().join(format!("search-{name}-{}.sh", std::process::id()));
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n"))?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+            Ok(path)
+        };
+
+        // the body is larger than the pipe buffers
+        let echo = script("echo", "[ \"$1\" = pdf-text ] && cat")?;
+        let body = "Ownership\n".repeat(100_000);
+        assert_eq!(run_pdf_text(&echo, body.clone().into_bytes()).await?, body);
+
+        // the crash of the extraction only fails the document
+        let abort = script("abort", "kill -ABRT $$")?;
+        assert!(run_pdf_text(&abort, b"%PDF-broken".to_vec()).await.is_err());
+
+        _ = std::fs::remove_file(echo);
+        _ = std::fs::remove_file(abort);
+        Ok(())
+    }
+}
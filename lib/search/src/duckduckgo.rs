@@ -0,0 +1,130 @@
+// Scrape the HTML version of DuckDuckGo, no API key is required
+use super::{SearchLink, SearchProvider, endpoint_or};
+use anyhow::Result;
+use async_trait::async_trait;
+use cutil::{http, reqwest};
+use scraper::{Html, Selector};
+
+pub const API_URL: &str = "https://html.duckduckgo.com/html/";
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub num: u8,
+
+    // Use `API_URL` if it's empty
+    pub endpoint: String,
+}
+
+// The result links look like `//duckduckgo.com/l/?uddg=<encoded url>&rut=...`
+fn decode_link(href: &str) -> Option<String> {
+    let Some((_, query)) = href.split_once('?') else {
+        return Some(href.to_string());
+    };
+
+    query
+        .split('&')
+        .filter_map(|item| item.split_once('='))
+        .find(|(key, _)| *key == "uddg")
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|value| value.to_string())
+}
+
+fn parse_links(html: &str, num: usize) -> Vec<SearchLink> {
+    let selector = Selector::parse("a.result__a").unwrap();
+    let document = Html::parse_document(html);
+
+    document
+        .select(&selector)
+        .filter_map(|item| {
+            let link = decode_link(item.value().attr("href")?)?;
+            let title = item.text().collect::<String>().trim().to_string();
+            Some(SearchLink { title, link })
+        })
+        .filter(|item| item.link.starts_with("http"))
+        .take(num)
+        .collect()
+}
+
+#[async_trait]
+impl SearchProvider for Config {
+    fn cache_key(&self) -> String {
+        format!(
+            "duckduckgo\n{}\n{}",
+            endpoint_or(&self.endpoint, API_URL),
+            self.num
+        )
+    }
+
+    fn num(&self) -> usize {
+        self.num as usize
+    }
+
+    async fn search_links(&self, query: &str) -> Result<Vec<SearchLink>> {
+        let url = format!(
+            "{}?q={}",
+            endpoint_or(&self.endpoint, API_URL),
+            urlencoding::encode(query)
+        );
+
+        let html = reqwest::Client::new()
+            .get(&url)
+            .headers(http::headers())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let links = parse_links(&html, self.num as usize);
+        log::info!("{:#?}", links);
+
+        Ok(links)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    const RESPONSE: &str = r#"<html><body>
+        <div class="result results_links web-result">
+            <h2 class="result__title">
+                <a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2F&amp;rut=abc">Rust <b>Programming</b> Language</a>
+            </h2>
+        </div>
+        <div class="result results_links web-result">
+            <h2 class="result__title">
+                <a rel="nofollow" class="result__a" href="https://doc.rust-lang.org/book/">The Book</a>
+            </h2>
+        </div>
+        <div class="result results_links web-result">
+            <a class="result__a" href="https://rustlings.cool/">Rustlings</a>
+        </div>
+    </body></html>"#;
+
+    #[tokio::test]
+    async fn duckduckgo_search_links() -> Result<()> {
+        let (endpoint, requests) = test_server::serve("text/html", RESPONSE).await;
+        let config = Config { num: 2, endpoint };
+
+        let links = config.search_links("learn rust").await?;
+        assert_eq!(
+            links,
+            vec![
+                SearchLink {
+                    title: "Rust Programming Language".to_string(),
+                    link: "https://www.rust-lang.org/".to_string(),
+                },
+                SearchLink {
+                    title: "The Book".to_string(),
+                    link: "https://doc.rust-lang.org/book/".to_string(),
+                },
+            ]
+        );
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.starts_with("GET /?q=learn%20rust "));
+        Ok(())
+    }
+}
@@ -0,0 +1,591 @@
+// Extract the main content of a web page like the reader mode of the browsers.
+// The text blocks are scored by their length and comma count, and the scores
+// are propagated to their containers. The best scored container and its
+// related siblings are rendered to text with the markdown flavor.
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::{HashMap, HashSet};
+
+// the text blocks shorter than this are not scored
+const MIN_BLOCK_CHARS: usize = 25;
+
+// a sibling of the top candidate whose score reaches this ratio is also kept
+const SIBLING_SCORE_RATIO: f64 = 0.2;
+
+const REMOVED_TAGS: &[&str] = &[
+    "aside", "button", "canvas", "dialog", "embed", "footer", "form", "iframe", "input", "nav",
+    "noscript", "object", "script", "select", "style", "svg", "template", "textarea",
+];
+
+const REMOVED_ROLES: &[&str] = &[
+    "alertdialog",
+    "banner",
+    "complementary",
+    "contentinfo",
+    "dialog",
+    "menu",
+    "menubar",
+    "navigation",
+];
+
+const UNLIKELY_HINTS: &[&str] = &[
+    "ads",
+    "advert",
+    "banner",
+    "breadcrumb",
+    "comment",
+    "consent",
+    "cookie",
+    "disqus",
+    "footer",
+    "header",
+    "masthead",
+    "menu",
+    "modal",
+    "nav",
+    "newsletter",
+    "pager",
+    "pagination",
+    "popup",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+    "sponsor",
+    "subscribe",
+    "toolbar",
+    "widget",
+];
+
+const LIKELY_HINTS: &[&str] = &[
+    "article", "blog", "body", "content", "entry", "main", "post", "story", "text",
+];
+
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "blockquote",
+    "div",
+    "dl",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+fn class_tokens(el: &ElementRef) -> Vec<String> {
+    let attrs = format!(
+        "{} {}",
+        el.value().attr("class").unwrap_or_default(),
+        el.value().id().unwrap_or_default()
+    );
+
+    attrs
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .collect()
+}
+
+fn has_hint(tokens: &[String], hints: &[&str]) -> bool {
+    tokens
+        .iter()
+        .any(|token| hints.iter().any(|hint| token.starts_with(hint)))
+}
+
+fn is_removed(el: &ElementRef) -> bool {
+    let value = el.value();
+    if REMOVED_TAGS.contains(&value.name())
+        || value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+        || value
+            .attr("role")
+            .is_some_and(|role| REMOVED_ROLES.contains(&role))
+        || value
+            .attr("style")
+            .is_some_and(|style| style.replace(' ', "").contains("display:none"))
+    {
+        return true;
+    }
+
+    if matches!(value.name(), "body" | "article" | "main") {
+        return false;
+    }
+
+    let tokens = class_tokens(el);
+    has_hint(&tokens, UNLIKELY_HINTS) && !has_hint(&tokens, LIKELY_HINTS)
+}
+
+fn class_weight(el: &ElementRef) -> f64 {
+    let tokens = class_tokens(el);
+    let mut weight = 0.0;
+
+    if has_hint(&tokens, LIKELY_HINTS) {
+        weight += 25.0;
+    }
+
+    if has_hint(&tokens, UNLIKELY_HINTS) {
+        weight -= 25.0;
+    }
+
+    weight
+}
+
+fn initial_score(el: &ElementRef) -> f64 {
+    let base = match el.value().name() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    base + class_weight(el)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn inner_text(node: NodeRef<Node>, removed: &HashSet<NodeId>) -> String {
+    let mut text = String::new();
+    for item in node.descendants() {
+        if let Node::Text(t) = item.value()
+            && !item.ancestors().any(|p| removed.contains(&p.id()))
+        {
+            text.push_str(t);
+            text.push(' ');
+        }
+    }
+
+    collapse_whitespace(&text)
+}
+
+fn link_density(node: NodeRef<Node>, removed: &HashSet<NodeId>) -> f64 {
+    let len = inner_text(node, removed).chars().count();
+    if len == 0 {
+        return 0.0;
+    }
+
+    let link_len: usize = node
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|item| item.value().name() == "a")
+        .map(|item| inner_text(*item, removed).chars().count())
+        .sum();
+
+    link_len as f64 / len as f64
+}
+
+fn is_text_block(el: &ElementRef) -> bool {
+    match el.value().name() {
+        "p" | "pre" | "td" | "blockquote" => true,
+        "div" | "section" => !el
+            .children()
+            .filter_map(ElementRef::wrap)
+            .any(|item| BLOCK_TAGS.contains(&item.value().name())),
+        _ => false,
+    }
+}
+
+fn removed_nodes(root: ElementRef) -> HashSet<NodeId> {
+    root.descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(is_removed)
+        .map(|item| item.id())
+        .collect()
+}
+
+fn score_nodes(root: ElementRef, removed: &HashSet<NodeId>) -> HashMap<NodeId, f64> {
+    let mut scores = HashMap::new();
+
+    for el in root.descendants().filter_map(ElementRef::wrap) {
+        if !is_text_block(&el) || el.ancestors().any(|p| removed.contains(&p.id())) {
+            continue;
+        }
+
+        if removed.contains(&el.id()) {
+            continue;
+        }
+
+        let text = inner_text(*el, removed);
+        let len = text.chars().count();
+        if len < MIN_BLOCK_CHARS {
+            continue;
+        }
+
+        let commas = text.chars().filter(|c| matches!(c, ',' | '，')).count();
+        let score = 1.0 + commas as f64 + f64::min(len as f64 / 100.0, 3.0);
+
+        for (level, ancestor) in el
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .take(2)
+            .enumerate()
+        {
+            let item = scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(&ancestor));
+            *item += score / (level + 1) as f64;
+        }
+    }
+
+    for (id, score) in scores.iter_mut() {
+        if let Some(node) = root.tree().get(*id) {
+            *score *= 1.0 - link_density(node, removed);
+        }
+    }
+
+    scores
+}
+
+// The top candidate and its related siblings in the document order
+fn content_nodes<'a>(
+    root: ElementRef<'a>,
+    scores: &HashMap<NodeId, f64>,
+    removed: &HashSet<NodeId>,
+) -> Vec<NodeRef<'a, Node>> {
+    let Some((top_id, top_score)) = scores
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(id, score)| (*id, *score))
+    else {
+        return vec![*root];
+    };
+
+    let top = root.tree().get(top_id).unwrap();
+    let Some(parent) = top.parent().filter(|_| top_id != root.id()) else {
+        return vec![top];
+    };
+
+    let threshold = f64::max(10.0, top_score * SIBLING_SCORE_RATIO);
+    parent
+        .children()
+        .filter(|item| {
+            if item.id() == top_id {
+                return true;
+            }
+
+            let Some(el) = ElementRef::wrap(*item) else {
+                return false;
+            };
+
+            if removed.contains(&el.id()) {
+                return false;
+            }
+
+            if scores
+                .get(&el.id())
+                .is_some_and(|score| *score >= threshold)
+            {
+                return true;
+            }
+
+            el.value().name() == "p"
+                && inner_text(*item, removed).chars().count() > 80
+                && link_density(*item, removed) < 0.25
+        })
+        .collect()
+}
+
+struct Renderer<'a> {
+    removed: &'a HashSet<NodeId>,
+    list_depth: usize,
+    out: String,
+}
+
+impl Renderer<'_> {
+    fn trim_end_spaces(&mut self) {
+        let len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(len);
+    }
+
+    fn newline(&mut self) {
+        self.trim_end_spaces();
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn block(&mut self) {
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        let starts_with_space = text.starts_with(char::is_whitespace);
+        let ends_with_space = text.ends_with(char::is_whitespace);
+        let text = collapse_whitespace(text);
+
+        if (starts_with_space || text.is_empty())
+            && !self.out.is_empty()
+            && !self.out.ends_with([' ', '\n'])
+        {
+            self.out.push(' ');
+        }
+
+        if text.is_empty() {
+            return;
+        }
+
+        self.out.push_str(&text);
+        if ends_with_space {
+            self.out.push(' ');
+        }
+    }
+
+    fn raw_text(node: NodeRef<Node>) -> String {
+        node.descendants()
+            .filter_map(|item| match item.value() {
+                Node::Text(t) => Some(t.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn children(&mut self, node: NodeRef<Node>) {
+        for item in node.children() {
+            self.render(item);
+        }
+    }
+
+    fn list(&mut self, node: NodeRef<Node>, ordered: bool) {
+        if self.list_depth == 0 {
+            self.block();
+        } else {
+            self.newline();
+        }
+
+        self.list_depth += 1;
+        let items = node
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|item| item.value().name() == "li" && !self.removed.contains(&item.id()));
+
+        for (index, item) in items.enumerate() {
+            self.newline();
+            self.out.push_str(&"  ".repeat(self.list_depth - 1));
+            if ordered {
+                self.out.push_str(&format!("{}. ", index + 1));
+            } else {
+                self.out.push_str("- ");
+            }
+            self.children(*item);
+        }
+        self.list_depth -= 1;
+
+        if self.list_depth == 0 {
+            self.block();
+        } else {
+            self.newline();
+        }
+    }
+
+    fn render(&mut self, node: NodeRef<Node>) {
+        let el = match node.value() {
+            Node::Text(text) => return self.push_inline(text),
+            Node::Element(el) => el,
+            _ => return,
+        };
+
+        if self.removed.contains(&node.id()) {
+            return;
+        }
+
+        match el.name() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = el.name()[1..].parse::<usize>().unwrap_or(1);
+                self.block();
+                self.out.push_str(&format!("{} ", "#".repeat(level)));
+                self.children(node);
+                self.block();
+            }
+            "pre" => {
+                self.block();
+                self.out.push_str("```\n");
+                self.out
+                    .push_str(Self::raw_text(node).trim_matches('\n').trim_end());
+                self.out.push_str("\n```");
+                self.block();
+            }
+            "code" | "kbd" | "samp" => {
+                let text = collapse_whitespace(&Self::raw_text(node));
+                if !text.is_empty() {
+                    self.push_inline(&format!("`{text}`"));
+                }
+            }
+            "ul" | "menu" => self.list(node, false),
+            "ol" => self.list(node, true),
+            "br" => self.newline(),
+            "hr" => {
+                self.block();
+                self.out.push_str("---");
+                self.block();
+            }
+            "img" | "picture" | "video" | "audio" | "head" | "title" | "meta" | "link" => (),
+            "tr" => {
+                self.newline();
+                self.children(node);
+                self.newline();
+            }
+            "td" | "th" => {
+                self.children(node);
+                self.out.push(' ');
+            }
+            "li" | "dt" | "dd" => {
+                self.newline();
+                self.children(node);
+                self.newline();
+            }
+            name if BLOCK_TAGS.contains(&name) || name == "body" => {
+                self.block();
+                self.children(node);
+                self.block();
+            }
+            _ => self.children(node),
+        }
+    }
+}
+
+// Return the main content of the html page, or `None` if it has no text
+pub fn main_content(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let body_selector = Selector::parse("body").unwrap();
+    let root = document
+        .select(&body_selector)
+        .next()
+        .unwrap_or(document.root_element());
+
+    let removed = removed_nodes(root);
+    let scores = score_nodes(root, &removed);
+    let nodes = content_nodes(root, &scores, &removed);
+
+    let mut renderer = Renderer {
+        removed: &removed,
+        list_depth: 0,
+        out: String::new(),
+    };
+
+    // The title is usually outside of the content container
+    let has_title = nodes.iter().any(|node| {
+        node.descendants()
+            .filter_map(ElementRef::wrap)
+            .any(|item| item.value().name() == "h1")
+    });
+
+    if !has_title {
+        let title_selector = Selector::parse("h1").unwrap();
+        if let Some(title) = root
+            .select(&title_selector)
+            .find(|item| !item.ancestors().any(|p| removed.contains(&p.id())))
+        {
+            renderer.render(*title);
+        }
+    }
+
+    for node in nodes {
+        renderer.render(node);
+    }
+
+    let text = renderer.out.trim().to_string();
+    if text.is_empty() { None } else { Some(text) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(html: &str, includes: &[&str], excludes: &[&str]) {
+        let text = main_content(html).unwrap();
+        for item in includes {
+            assert!(text.contains(item), "missing `{item}` in:\n{text}");
+        }
+
+        for item in excludes {
+            assert!(!text.contains(item), "unexpected `{item}` in:\n{text}");
+        }
+    }
+
+    #[test]
+    fn extract_blog() {
+        check(
+            include_str!("../fixtures/blog.html"),
+            &[
+                "# Understanding Ownership in Rust",
+                "the borrow checker, which is described in the official book,",
+                "- Each value in Rust has an owner.",
+                "- There can only be one owner at a time.",
+                "```\nfn main() {\n    let s1 = String::from(\"hello\");\n    let s2 = s1;\n}\n```",
+                "## Moves and clones",
+                "calling `clone`",
+            ],
+            &[
+                "We use cookies",
+                "Home",
+                "Leave a comment",
+                "Popular posts",
+                "All rights reserved",
+                "Subscribe to the newsletter",
+            ],
+        );
+    }
+
+    #[test]
+    fn extract_news() {
+        check(
+            include_str!("../fixtures/news.html"),
+            &[
+                "# City council approves new bike lanes",
+                "The council voted 7 to 2 on Tuesday evening",
+                "1. Main Street, from the station to the river,",
+                "2. Harbor Road, along the waterfront,",
+            ],
+            &["Share on", "Related stories", "Sign in", "Advertisement"],
+        );
+    }
+
+    #[test]
+    fn extract_docs() {
+        check(
+            include_str!("../fixtures/docs.html"),
+            &[
+                "# Configuration",
+                "## Environment variables",
+                "`APP_PORT`",
+                "```\n[server]\nport = 8080\nhost = \"0.0.0.0\"\n```",
+                "- `debug`: enable the verbose logs",
+                "  - `trace`: log every request",
+            ],
+            &[
+                "Getting started",
+                "Edit this page",
+                "Previous",
+                "Search the docs",
+            ],
+        );
+    }
+
+    #[test]
+    fn extract_empty() {
+        assert_eq!(
+            main_content("<html><body><nav>Home</nav></body></html>"),
+            None
+        );
+    }
+}
@@ -1,11 +1,14 @@
-use super::{req_link, SearchItem, SearchLink};
+use super::{SearchLink, SearchProvider, endpoint_or};
 use anyhow::Result;
+use async_trait::async_trait;
 use cutil::reqwest;
 use serde::Deserialize;
-use std::sync::Arc;
+
+pub const API_URL: &str = "https://www.googleapis.com/customsearch/v1";
 
 #[derive(Deserialize, Debug)]
 struct SearchResult {
+    #[serde(default)]
     items: Vec<SearchResultItem>,
 }
 
@@ -15,11 +18,14 @@ struct SearchResultItem {
     link: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Config {
     pub cx: String,
     pub api_key: String,
     pub num: u8,
+
+    // Use `API_URL` if it's empty
+    pub endpoint: String,
 }
 
 impl From<&SearchResultItem> for SearchLink {
@@ -31,54 +37,70 @@ impl From<&SearchResultItem> for SearchLink {
     }
 }
 
-pub async fn search(query: &str, config: Config) -> Result<(Option<String>, Vec<SearchLink>)> {
-    let url = format!(
-        "https://www.googleapis.com/customsearch/v1?key={}&cx={}&num={}&start=1&q={}",
-        config.api_key,
-        config.cx,
-        config.num,
-        urlencoding::encode(query)
-    );
-
-    let gs = reqwest::get(&url).await?.json::<SearchResult>().await?;
-    log::info!("{:#?}", gs);
-
-    let search_links = gs
-        .items
-        .iter()
-        .map(|item| item.into())
-        .collect::<Vec<SearchLink>>();
-
-    let (sender, mut receiver) = tokio::sync::mpsc::channel(10);
-    let (sender, mut bris) = (Arc::new(sender), vec![]);
-
-    for item in gs.items.into_iter() {
-        if item.link.is_empty() {
-            continue;
-        }
+#[async_trait]
+impl SearchProvider for Config {
+    fn cache_key(&self) -> String {
+        format!(
+            "google\n{}\n{}\n{}",
+            endpoint_or(&self.endpoint, API_URL),
+            self.cx,
+            self.num
+        )
+    }
 
-        let sender = sender.clone();
-        tokio::spawn(async move {
-            if let Ok(Some(contents)) = req_link(&item.link).await {
-                _ = sender
-                    .send(SearchItem {
-                        title: item.title,
-                        contents,
-                    })
-                    .await;
-            }
-        });
+    fn num(&self) -> usize {
+        self.num as usize
     }
 
-    drop(sender);
+    async fn search_links(&self, query: &str) -> Result<Vec<SearchLink>> {
+        let url = format!(
+            "{}?key={}&cx={}&num={}&start=1&q={}",
+            endpoint_or(&self.endpoint, API_URL),
+            self.api_key,
+            self.cx,
+            self.num,
+            urlencoding::encode(query)
+        );
 
-    while let Some(item) = receiver.recv().await {
-        bris.push(item);
-    }
+        let gs = reqwest::get(&url)
+            .await?
+            .error_for_status()?
+            .json::<SearchResult>()
+            .await?;
+        log::info!("{:#?}", gs);
 
-    if bris.is_empty() {
-        return Ok((None, search_links));
+        Ok(gs.items.iter().map(|item| item.into()).collect())
     }
+}
 
-    Ok((Some(serde_json::to_string(&bris).unwrap()), search_links))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    const RESPONSE: &str = r#"{
+        "items": [
+            {"title": "Rust", "link": "https://www.rust-lang.org/"},
+            {"title": "The Book", "link": "https://doc.rust-lang.org/book/"}
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn google_search_links() -> Result<()> {
+        let (endpoint, requests) = test_server::serve("application/json", RESPONSE).await;
+        let config = Config {
+            cx: "cx".to_string(),
+            api_key: "key".to_string(),
+            num: 2,
+            endpoint,
+        };
+
+        let links = config.search_links("learn rust").await?;
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[1].link, "https://doc.rust-lang.org/book/");
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.starts_with("GET /?key=key&cx=cx&num=2&start=1&q=learn%20rust "));
+        Ok(())
+    }
 }
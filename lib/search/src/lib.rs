@@ -1,60 +1,390 @@
 use anyhow::Result;
-use cutil::{http, reqwest};
-use html2text::from_read;
-use html5ever::tree_builder::TreeSink;
-use scraper::{Html, HtmlTreeSink, Selector};
+use async_trait::async_trait;
+use cache::{Cache, PageEntry};
+use cutil::{
+    http,
+    reqwest::{
+        self, StatusCode,
+        header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    },
+};
+use document::DocumentType;
+use once_cell::sync::Lazy;
+use rank::{Chunk, RankOptions};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
+pub mod bing;
+pub mod brave;
+pub mod cache;
+pub mod document;
+pub mod duckduckgo;
+pub mod extract;
 pub mod google;
+pub mod rank;
+pub mod searxng;
+
+#[cfg(test)]
+mod test_server;
+
+// The max bytes of a downloaded document
+const MAX_DOWNLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+// The max concurrent requests to a host, so a site which appears
+// in many links is not flooded
+const MAX_HOST_REQUESTS: usize = 2;
+
+static HOST_PERMITS: Lazy<Mutex<HashMap<String, Arc<Semaphore>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static URL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"https?://[^\s<>"'`\[\]{}]+"#).unwrap());
 
 #[derive(Serialize, Debug, Clone)]
 struct SearchItem {
+    // the number of the link, starts from 1
+    source: usize,
     title: String,
     contents: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SearchLink {
     pub title: String,
     pub link: String,
 }
 
-async fn req_link(link: &str) -> Result<Option<String>> {
-    let client = reqwest::Client::new();
-    let headers = http::headers();
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    async fn search_links(&self, query: &str) -> Result<Vec<SearchLink>>;
+
+    // Identify the provider and the options which change the found links
+    fn cache_key(&self) -> String;
+
+    // The maximal number of the links of a search
+    fn num(&self) -> usize;
+
+    // Use the cached links of the same query if they are not expired
+    async fn cached_search_links(&self, query: &str) -> Result<Vec<SearchLink>> {
+        let Some(cache) = cache::current() else {
+            return self.search_links(query).await;
+        };
+
+        let key = format!("{}\n{query}", self.cache_key());
+        if let Some(links) = cache.links(&key) {
+            log::info!("use the cached links of `{query}`");
+            return Ok(links);
+        }
+
+        let links = self.search_links(query).await?;
+        if !links.is_empty() {
+            cache.save_links(&key, &links);
+        }
+
+        Ok(links)
+    }
+
+    // Return the relevant chunks of the found web pages in JSON and the links
+    async fn search(
+        &self,
+        query: &str,
+        options: &RankOptions,
+    ) -> Result<(Option<String>, Vec<SearchLink>)> {
+        self.search_queries(&[query.to_string()], options).await
+    }
+
+    // Search all the queries and merge their links, which are no more than `num`.
+    // A failed query is skipped unless all of them fail.
+    // The chunks are ranked against all the queries.
+    async fn search_queries(
+        &self,
+        queries: &[String],
+        options: &RankOptions,
+    ) -> Result<(Option<String>, Vec<SearchLink>)> {
+        let (mut links, mut error) = (vec![], None);
+        for query in queries {
+            match self.cached_search_links(query).await {
+                Ok(items) => links.push(items),
+                Err(e) => {
+                    log::warn!("search `{query}` failed: {e:?}");
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        if links.is_empty()
+            && let Some(e) = error
+        {
+            return Err(e);
+        }
+
+        let search_links = merge_links(links, self.num());
+        let pages = fetch_pages(&search_links).await;
+        let chunks = rank::top_chunks(&queries.join(" "), &pages, options);
+        Ok((web_context(&search_links, chunks), search_links))
+    }
+}
+
+// Use `default` if `endpoint` is empty
+fn endpoint_or<'a>(endpoint: &'a str, default: &'a str) -> &'a str {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    if endpoint.is_empty() {
+        default
+    } else {
+        endpoint
+    }
+}
+
+// The trailing punctuations and the unbalanced `)` are not parts of the url,
+// e.g. `(see https://example.com.)`. The balanced parentheses are kept,
+// e.g. `https://en.wikipedia.org/wiki/Rust_(programming_language)`.
+fn trim_url_end(mut url: &str) -> &str {
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '。', '，']);
+        url = match trimmed.strip_suffix(')') {
+            Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => rest,
+            _ => return trimmed,
+        };
+    }
+}
+
+// Find the distinct urls in the text
+pub fn find_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = vec![];
+
+    for item in URL_RE.find_iter(text) {
+        let url = trim_url_end(item.as_str());
+
+        if reqwest::Url::parse(url).is_ok_and(|item| item.host_str().is_some())
+            && !urls.iter().any(|item| item == url)
+        {
+            urls.push(url.to_string());
+        }
+    }
+
+    urls
+}
+
+// Interleave the links of the queries, so the top links of each query come first.
+// The duplicated links are removed, and only the first `num` links are kept.
+pub fn merge_links(links: Vec<Vec<SearchLink>>, num: usize) -> Vec<SearchLink> {
+    let max_len = links
+        .iter()
+        .map(|item| item.len())
+        .max()
+        .unwrap_or_default();
+    let mut merged: Vec<SearchLink> = vec![];
+
+    for index in 0..max_len {
+        for item in links.iter().filter_map(|items| items.get(index)) {
+            let link = item.link.trim_end_matches('/');
+            if !merged
+                .iter()
+                .any(|merged| merged.link.trim_end_matches('/') == link)
+            {
+                merged.push(item.clone());
+            }
+        }
+    }
+
+    merged.truncate(num);
+    merged
+}
+
+// Request the links concurrently, the failed links are skipped.
+// Return the indexes of the links and the page contents.
+pub async fn fetch_pages(search_links: &[SearchLink]) -> Vec<(usize, String)> {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(10);
+    let (sender, mut pages) = (Arc::new(sender), vec![]);
 
-    if let Ok(html_content) = client
+    for (index, item) in search_links.iter().cloned().enumerate() {
+        if item.link.is_empty() {
+            continue;
+        }
+
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            if let Ok(Some(contents)) = req_link(&item.link).await {
+                _ = sender.send((index, contents)).await;
+            }
+        });
+    }
+
+    drop(sender);
+
+    while let Some(item) = receiver.recv().await {
+        pages.push(item);
+    }
+
+    pages.sort_by_key(|item| item.0);
+    pages
+}
+
+// The chunks in JSON, each one is tagged with the number of its link
+pub fn web_context(search_links: &[SearchLink], chunks: Vec<Chunk>) -> Option<String> {
+    if chunks.is_empty() {
+        return None;
+    }
+
+    let items = chunks
+        .into_iter()
+        .map(|item| SearchItem {
+            source: item.source + 1,
+            title: search_links
+                .get(item.source)
+                .map(|link| link.title.clone())
+                .unwrap_or_default(),
+            contents: item.text,
+        })
+        .collect::<Vec<_>>();
+
+    Some(serde_json::to_string(&items).unwrap())
+}
+
+async fn acquire_host_permit(link: &str) -> Option<OwnedSemaphorePermit> {
+    let host = reqwest::Url::parse(link).ok()?.host_str()?.to_string();
+    let semaphore = HOST_PERMITS
+        .lock()
+        .unwrap()
+        .entry(host)
+        .or_insert_with(|| Arc::new(Semaphore::new(MAX_HOST_REQUESTS)))
+        .clone();
+
+    semaphore.acquire_owned().await.ok()
+}
+
+pub async fn req_link(link: &str) -> Result<Option<String>> {
+    fetch_page(link, cache::current().as_ref()).await
+}
+
+// Return the fresh cached page directly. The expired one is revalidated
+// with its `ETag` or `Last-Modified`, and reused if it's not modified.
+// The document is converted to text by its content type.
+async fn fetch_page(link: &str, cache: Option<&Cache>) -> Result<Option<String>> {
+    let cached = cache.and_then(|item| item.page(link));
+    if let (Some(cache), Some(entry)) = (cache, cached.as_ref())
+        && cache.is_fresh(entry.timestamp)
+    {
+        return Ok(entry.content.clone());
+    }
+
+    let _permit = acquire_host_permit(link).await;
+
+    let mut headers = http::headers();
+    if let Some(entry) = cached.as_ref() {
+        if let Some(etag) = entry.etag.as_ref().and_then(|item| item.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = entry
+            .last_modified
+            .as_ref()
+            .and_then(|item| item.parse().ok())
+        {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let resp = reqwest::Client::new()
         .get(link)
         .headers(headers)
         .timeout(std::time::Duration::from_secs(10))
         .send()
-        .await?
-        .text()
-        .await
+        .await?;
+
+    if resp.status() == StatusCode::NOT_MODIFIED
+        && let Some(entry) = cached
     {
-        let body_selector = Selector::parse("body").unwrap();
-        let remove_selector = Selector::parse("a,script,svg,style").unwrap();
-        let document = Html::parse_document(&html_content);
+        let content = entry.content.clone();
+        if let Some(cache) = cache {
+            cache.save_page(link, entry);
+        }
+        return Ok(content);
+    }
+
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|item| item.to_str().ok())
+            .map(|item| item.to_string())
+    };
+    let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+    let content_type = header(CONTENT_TYPE);
+    let is_success = resp.status().is_success();
+
+    let content = match document::document_type(content_type.as_deref(), link) {
+        DocumentType::Binary => {
+            log::info!("skip the binary document {link}: {content_type:?}");
+            None
+        }
+        ty => {
+            let body = document::read_body(resp, MAX_DOWNLOAD_BYTES).await?;
+            document::to_text(ty, body, content_type.as_deref()).await?
+        }
+    };
 
-        if let Some(body) = document.select(&body_selector).next() {
-            let body_html = body.html().to_string();
-            let document = Html::parse_document(&body_html);
+    if let Some(cache) = cache
+        && is_success
+    {
+        let entry = PageEntry {
+            etag,
+            last_modified,
+            content: content.clone(),
+            ..Default::default()
+        };
+        cache.save_page(link, entry);
+    }
 
-            let node_ids: Vec<_> = document.select(&remove_selector).map(|x| x.id()).collect();
+    Ok(content)
+}
 
-            let sink = HtmlTreeSink::new(document);
-            for id in node_ids {
-                sink.remove_from_parent(&id);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let html = sink.0.borrow().html();
-            let content = from_read(html.as_bytes(), 80)?.to_string();
+    fn links(items: &[&str]) -> Vec<SearchLink> {
+        items
+            .iter()
+            .map(|item| SearchLink {
+                title: item.to_string(),
+                link: format!("https://example.com/{item}"),
+            })
+            .collect()
+    }
 
-            if !content.is_empty() {
-                return Ok(Some(content));
-            }
-        }
+    #[test]
+    fn find_text_urls() {
+        let text = "Summarize https://example.com/a?b=1, and (https://example.com/a?b=1).\n\
+                    See http://localhost:8080/docs/。 And ftp://example.com or https://\n\
+                    (see https://en.wikipedia.org/wiki/Rust_(programming_language)), \
+                    https://example.com/f(x).";
+        assert_eq!(
+            find_urls(text),
+            vec![
+                "https://example.com/a?b=1",
+                "http://localhost:8080/docs/",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                "https://example.com/f(x)"
+            ]
+        );
     }
 
-    Ok(None)
+    #[test]
+    fn merge_query_links() {
+        let mut second = links(&["b", "c"]);
+        second[0].link.push('/');
+
+        let titles = |num| {
+            merge_links(vec![links(&["a", "b", "d"]), second.clone(), vec![]], num)
+                .into_iter()
+                .map(|item| item.title)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(10), vec!["a", "b", "c", "d"]);
+        assert_eq!(titles(3), vec!["a", "b", "c"]);
+    }
 }
@@ -0,0 +1,334 @@
+// Split the fetched pages into chunks and rank them against the query with BM25,
+// so only the relevant parts of the pages are sent to the model.
+use std::collections::HashMap;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+#[derive(Debug, Clone)]
+pub struct RankOptions {
+    // the max characters of a chunk
+    pub chunk_chars: usize,
+
+    // the max count of the selected chunks
+    pub top_k: usize,
+
+    // the max estimated tokens of the selected chunks
+    pub max_tokens: usize,
+}
+
+impl Default for RankOptions {
+    fn default() -> Self {
+        Self {
+            chunk_chars: 1200,
+            top_k: 8,
+            max_tokens: 3000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    // the index of the page in the search links
+    pub source: usize,
+
+    // the position of the chunk in the page
+    pub position: usize,
+
+    pub text: String,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}')
+}
+
+// The lowercase words, each CJK character is a token
+pub fn tokenize(text: &str) -> Vec<String> {
+    let (mut tokens, mut word) = (vec![], String::new());
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+    }
+
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+
+    tokens
+}
+
+// A rough estimation without a tokenizer: about 4 ASCII characters
+// or 1 non-ASCII character per token
+pub fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(|c| c.is_ascii()).count();
+    let others = text.chars().count() - ascii;
+    ascii.div_ceil(4) + others
+}
+
+fn split_long(text: &str, max_chars: usize) -> Vec<String> {
+    let (mut items, mut current) = (vec![], String::new());
+
+    for word in text.split_inclusive(char::is_whitespace) {
+        if current.chars().count() + word.chars().count() > max_chars && !current.is_empty() {
+            items.push(current.trim().to_string());
+            current.clear();
+        }
+
+        // a very long word, e.g. CJK text without spaces
+        if word.chars().count() > max_chars {
+            let chars = word.chars().collect::<Vec<_>>();
+            for part in chars.chunks(max_chars) {
+                items.push(part.iter().collect::<String>().trim().to_string());
+            }
+            continue;
+        }
+
+        current.push_str(word);
+    }
+
+    if !current.trim().is_empty() {
+        items.push(current.trim().to_string());
+    }
+
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+// Merge the paragraphs into chunks of at most `max_chars` characters.
+// A paragraph longer than `max_chars` is split at the whitespaces.
+pub fn split_chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let (mut chunks, mut current) = (vec![], String::new());
+
+    let paragraphs = text
+        .split("\n\n")
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .flat_map(|item| {
+            if item.chars().count() > max_chars {
+                split_long(item, max_chars)
+            } else {
+                vec![item.to_string()]
+            }
+        });
+
+    for paragraph in paragraphs {
+        let len = current.chars().count();
+        if len > 0 && len + 2 + paragraph.chars().count() > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&paragraph);
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+pub struct Bm25 {
+    docs: Vec<HashMap<String, usize>>,
+    lens: Vec<usize>,
+    doc_freqs: HashMap<String, usize>,
+    avg_len: f64,
+}
+
+impl Bm25 {
+    pub fn new<T: AsRef<str>>(docs: &[T]) -> Self {
+        let (mut items, mut lens, mut doc_freqs) = (vec![], vec![], HashMap::new());
+
+        for doc in docs {
+            let tokens = tokenize(doc.as_ref());
+            lens.push(tokens.len());
+
+            let mut freqs = HashMap::new();
+            for token in tokens {
+                *freqs.entry(token).or_insert(0) += 1;
+            }
+
+            for token in freqs.keys() {
+                *doc_freqs.entry(token.clone()).or_insert(0) += 1;
+            }
+
+            items.push(freqs);
+        }
+
+        let avg_len = if lens.is_empty() {
+            0.0
+        } else {
+            lens.iter().sum::<usize>() as f64 / lens.len() as f64
+        };
+
+        Self {
+            docs: items,
+            lens,
+            doc_freqs,
+            avg_len,
+        }
+    }
+
+    fn idf(&self, token: &str) -> f64 {
+        let n = self.docs.len() as f64;
+        let df = self.doc_freqs.get(token).copied().unwrap_or_default() as f64;
+        ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+    }
+
+    // The scores of all the documents in order
+    pub fn scores(&self, query: &str) -> Vec<f64> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        self.docs
+            .iter()
+            .zip(self.lens.iter())
+            .map(|(freqs, len)| {
+                let norm = if self.avg_len > 0.0 {
+                    1.0 - BM25_B + BM25_B * *len as f64 / self.avg_len
+                } else {
+                    1.0
+                };
+
+                terms
+                    .iter()
+                    .filter_map(|term| freqs.get(term).map(|tf| (term, *tf as f64)))
+                    .map(|(term, tf)| self.idf(term) * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm))
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+// Return the most relevant chunks of the pages within the token budget,
+// ordered by their sources and positions. The leading chunks of the pages
+// are preferred if the scores are equal, e.g. no word of the query matches.
+pub fn top_chunks(query: &str, pages: &[(usize, String)], options: &RankOptions) -> Vec<Chunk> {
+    let chunks = pages
+        .iter()
+        .flat_map(|(source, text)| {
+            split_chunks(text, options.chunk_chars)
+                .into_iter()
+                .enumerate()
+                .map(|(position, text)| Chunk {
+                    source: *source,
+                    position,
+                    text,
+                })
+        })
+        .collect::<Vec<_>>();
+
+    let texts = chunks.iter().map(|item| &item.text).collect::<Vec<_>>();
+    let scores = Bm25::new(&texts).scores(query);
+
+    let mut ranked = chunks.into_iter().zip(scores).collect::<Vec<_>>();
+    ranked.sort_by(|a, b| {
+        b.1.total_cmp(&a.1)
+            .then(a.0.position.cmp(&b.0.position))
+            .then(a.0.source.cmp(&b.0.source))
+    });
+
+    let (mut selected, mut tokens) = (vec![], 0);
+    for (chunk, _) in ranked {
+        if selected.len() >= options.top_k {
+            break;
+        }
+
+        let chunk_tokens = estimate_tokens(&chunk.text);
+        if tokens + chunk_tokens > options.max_tokens {
+            continue;
+        }
+
+        tokens += chunk_tokens;
+        selected.push(chunk);
+    }
+
+    selected.sort_by_key(|item| (item.source, item.position));
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_text() {
+        assert_eq!(
+            tokenize("Hello, Rust-lang 2024! 你好"),
+            vec!["hello", "rust", "lang", "2024", "你", "好"]
+        );
+    }
+
+    #[test]
+    fn split_text_chunks() {
+        let text = "aaaa aaaa\n\nbbbb\n\n\n\ncccc cccc cccc cccc";
+        assert_eq!(
+            split_chunks(text, 16),
+            vec!["aaaa aaaa\n\nbbbb", "cccc cccc cccc", "cccc"]
+        );
+
+        assert_eq!(split_chunks("一二三四五", 2), vec!["一二", "三四", "五"]);
+        assert!(split_chunks(" \n\n ", 10).is_empty());
+    }
+
+    #[test]
+    fn bm25_scores() {
+        let docs = [
+            "the cat sits on the mat",
+            "rust ownership and borrowing rules",
+            "the dog and the cat",
+        ];
+
+        let scores = Bm25::new(&docs).scores("cat on the mat");
+        assert!(scores[0] > scores[2]);
+        assert_eq!(scores[1], 0.0);
+    }
+
+    #[test]
+    fn top_chunks_budget() {
+        let pages = vec![
+            (
+                0,
+                "Weather is sunny today.\n\nRust ownership moves values.".to_string(),
+            ),
+            (1, "Borrowing in Rust needs ownership.".to_string()),
+        ];
+
+        let options = RankOptions {
+            chunk_chars: 40,
+            top_k: 2,
+            max_tokens: 100,
+        };
+
+        let chunks = top_chunks("rust ownership", &pages, &options);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|item| (item.source, item.position))
+                .collect::<Vec<_>>(),
+            vec![(0, 1), (1, 0)]
+        );
+
+        let options = RankOptions {
+            max_tokens: estimate_tokens("Rust ownership moves values."),
+            ..options
+        };
+        let chunks = top_chunks("rust ownership", &pages, &options);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, "Rust ownership moves values.");
+    }
+}
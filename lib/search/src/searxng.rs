@@ -0,0 +1,113 @@
+// A self-hosted SearXNG instance. The `json` format should be
+// enabled in the `search.formats` of the instance settings.
+use super::{SearchLink, SearchProvider};
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use cutil::{http, reqwest};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+struct SearchResult {
+    #[serde(default)]
+    results: Vec<SearchResultItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchResultItem {
+    #[serde(default)]
+    title: String,
+    url: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    // e.g. `http://localhost:8080`
+    pub base_url: String,
+    pub num: u8,
+}
+
+#[async_trait]
+impl SearchProvider for Config {
+    fn cache_key(&self) -> String {
+        format!("searxng\n{}\n{}", self.base_url.trim(), self.num)
+    }
+
+    fn num(&self) -> usize {
+        self.num as usize
+    }
+
+    async fn search_links(&self, query: &str) -> Result<Vec<SearchLink>> {
+        let base_url = self.base_url.trim().trim_end_matches('/');
+        if base_url.is_empty() {
+            bail!("the SearXNG url is empty");
+        }
+
+        let url = format!(
+            "{base_url}/search?q={}&format=json",
+            urlencoding::encode(query)
+        );
+
+        let result = reqwest::Client::new()
+            .get(&url)
+            .headers(http::headers())
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SearchResult>()
+            .await?;
+        log::info!("{:#?}", result);
+
+        Ok(result
+            .results
+            .into_iter()
+            .take(self.num as usize)
+            .map(|item| SearchLink {
+                title: item.title,
+                link: item.url,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    const RESPONSE: &str = r#"{
+        "query": "learn rust",
+        "results": [
+            {"title": "Rust", "url": "https://www.rust-lang.org/", "engine": "bing"},
+            {"title": "The Book", "url": "https://doc.rust-lang.org/book/", "engine": "google"},
+            {"title": "Rustlings", "url": "https://rustlings.cool/", "engine": "google"}
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn searxng_search_links() -> Result<()> {
+        let (base_url, requests) = test_server::serve("application/json", RESPONSE).await;
+        let config = Config {
+            base_url: format!("{base_url}/"),
+            num: 2,
+        };
+
+        let links = config.search_links("learn rust").await?;
+        assert_eq!(
+            links,
+            vec![
+                SearchLink {
+                    title: "Rust".to_string(),
+                    link: "https://www.rust-lang.org/".to_string(),
+                },
+                SearchLink {
+                    title: "The Book".to_string(),
+                    link: "https://doc.rust-lang.org/book/".to_string(),
+                },
+            ]
+        );
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.starts_with("GET /search?q=learn%20rust&format=json "));
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{Arc, Mutex};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+pub fn response(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+// Serve the canned `body` for all the requests. Return the base url
+// and the received requests.
+pub async fn serve(
+    content_type: &'static str,
+    body: &'static str,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    serve_with(move |_| response("200 OK", &format!("Content-Type: {content_type}\r\n"), body))
+        .await
+}
+
+// Serve the responses returned by `handler` for the received requests
+pub async fn serve_with(
+    handler: impl Fn(&str) -> String + Send + Sync + 'static,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(vec![]));
+    let handler = Arc::new(handler);
+
+    let received = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let (received, handler) = (received.clone(), handler.clone());
+            tokio::spawn(async move {
+                let mut buf = vec![0; 8192];
+                let n = stream.read(&mut buf).await.unwrap_or_default();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+
+                let response = handler(&request);
+                received.lock().unwrap().push(request);
+                _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    (format!("http://{addr}"), requests)
+}
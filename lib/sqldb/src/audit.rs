@@ -0,0 +1,162 @@
+use super::pool;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_SELECT_LIMIT: i64 = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, sqlx::FromRow)]
+pub struct AuditEntry {
+    // assigned by the database, ignored by `insert`
+    pub id: i64,
+    pub timestamp: String,
+    pub session_uuid: String,
+    pub server: String,
+    pub tool: String,
+    pub arguments: String,
+    pub result_size: i64,
+    pub duration_ms: i64,
+    pub outcome: String,
+}
+
+// The empty fields match all the entries
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    // match the server, tool and arguments
+    pub keyword: String,
+    pub session_uuid: String,
+    pub outcome: String,
+
+    // fallback to `DEFAULT_SELECT_LIMIT`
+    pub limit: Option<i64>,
+}
+
+pub async fn new(table: &str) -> Result<()> {
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+             id INTEGER PRIMARY KEY,
+             timestamp TEXT NOT NULL,
+             session_uuid TEXT NOT NULL,
+             server TEXT NOT NULL,
+             tool TEXT NOT NULL,
+             arguments TEXT NOT NULL,
+             result_size INTEGER NOT NULL,
+             duration_ms INTEGER NOT NULL,
+             outcome TEXT NOT NULL
+             )"
+    ))
+    .execute(&pool().await)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn insert(table: &str, entry: &AuditEntry) -> Result<()> {
+    sqlx::query(&format!(
+        "INSERT INTO {table} (timestamp, session_uuid, server, tool, arguments, result_size, duration_ms, outcome)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    ))
+    .bind(&entry.timestamp)
+    .bind(&entry.session_uuid)
+    .bind(&entry.server)
+    .bind(&entry.tool)
+    .bind(&entry.arguments)
+    .bind(entry.result_size)
+    .bind(entry.duration_ms)
+    .bind(&entry.outcome)
+    .execute(&pool().await)
+    .await?;
+
+    Ok(())
+}
+
+// The latest entries first
+pub async fn select(table: &str, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+    let keyword = format!("%{}%", filter.keyword);
+
+    Ok(sqlx::query_as::<_, AuditEntry>(&format!(
+        "SELECT * FROM {table}
+         WHERE (server LIKE ?1 OR tool LIKE ?1 OR arguments LIKE ?1)
+           AND (?2 = '' OR session_uuid = ?2)
+           AND (?3 = '' OR outcome = ?3)
+         ORDER BY id DESC LIMIT ?4"
+    ))
+    .bind(keyword)
+    .bind(&filter.session_uuid)
+    .bind(&filter.outcome)
+    .bind(filter.limit.unwrap_or(DEFAULT_SELECT_LIMIT))
+    .fetch_all(&pool().await)
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use once_cell::sync::Lazy;
+    use tokio::sync::Mutex;
+
+    static MTX: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+    const DB_PATH: &str = "/tmp/audit-db-test.db";
+    const TABLE_NAME: &str = "audit_test";
+
+    async fn init(db_path: &str) {
+        super::super::create_db(db_path).await.expect("create db");
+        new(TABLE_NAME).await.expect("audit table failed");
+        super::super::entry::delete_all(TABLE_NAME)
+            .await
+            .expect("delete all failed");
+    }
+
+    fn entry(server: &str, tool: &str, session_uuid: &str, outcome: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: "2025-01-01 00:00:00".to_string(),
+            session_uuid: session_uuid.to_string(),
+            server: server.to_string(),
+            tool: tool.to_string(),
+            arguments: r#"{"path": "/tmp"}"#.to_string(),
+            result_size: 10,
+            duration_ms: 20,
+            outcome: outcome.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_select() -> Result<()> {
+        let _mtx = MTX.lock().await;
+        init(DB_PATH).await;
+
+        insert(TABLE_NAME, &entry("fs", "read_file", "uuid-1", "success")).await?;
+        insert(TABLE_NAME, &entry("fs", "write_file", "uuid-1", "denied")).await?;
+        insert(TABLE_NAME, &entry("git", "git_log", "uuid-2", "success")).await?;
+
+        let items = select(TABLE_NAME, &AuditFilter::default()).await?;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].tool, "git_log");
+        assert_eq!(items[2].tool, "read_file");
+        assert_eq!(items[2].result_size, 10);
+        assert_eq!(items[2].duration_ms, 20);
+
+        let filter = AuditFilter {
+            keyword: "file".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(select(TABLE_NAME, &filter).await?.len(), 2);
+
+        let filter = AuditFilter {
+            session_uuid: "uuid-1".to_string(),
+            outcome: "success".to_string(),
+            ..Default::default()
+        };
+        let items = select(TABLE_NAME, &filter).await?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].tool, "read_file");
+
+        let filter = AuditFilter {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(select(TABLE_NAME, &filter).await?.len(), 1);
+
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ use sqlx::{
 };
 use tokio::sync::Mutex;
 
+pub mod audit;
 pub mod entry;
 
 const MAX_CONNECTIONS: u32 = 3;